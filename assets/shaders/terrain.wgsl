@@ -0,0 +1,62 @@
+#import bevy_pbr::{
+    pbr_fragment::pbr_input_from_standard_material,
+    pbr_functions::{alpha_discard, apply_pbr_lighting, main_pass_post_lighting_processing},
+    forward_io::{VertexOutput, FragmentOutput},
+}
+
+struct TerrainUniform {
+    origin: vec2<f32>,
+    size: f32,
+    texture_scale: f32,
+    vertex_color_mix: f32,
+}
+
+@group(1) @binding(100) var<uniform> terrain: TerrainUniform;
+@group(1) @binding(101) var splat_texture: texture_2d<f32>;
+@group(1) @binding(102) var splat_sampler: sampler;
+@group(1) @binding(103) var sand_texture: texture_2d<f32>;
+@group(1) @binding(104) var layer_sampler: sampler;
+@group(1) @binding(105) var grass_texture: texture_2d<f32>;
+@group(1) @binding(106) var rock_texture: texture_2d<f32>;
+@group(1) @binding(107) var snow_texture: texture_2d<f32>;
+
+// Projects the texture along all three axes and blends them by how much the surface
+// faces each of them, so cliffs aren't stretched like a top down projection would.
+fn triplanar(layer: texture_2d<f32>, position: vec3<f32>, weights: vec3<f32>) -> vec4<f32> {
+    let scaled = position / terrain.texture_scale;
+    let x = textureSample(layer, layer_sampler, scaled.zy);
+    let y = textureSample(layer, layer_sampler, scaled.xz);
+    let z = textureSample(layer, layer_sampler, scaled.xy);
+    return x * weights.x + y * weights.y + z * weights.z;
+}
+
+@fragment
+fn fragment(in: VertexOutput, @builtin(front_facing) is_front: bool) -> FragmentOutput {
+    var pbr_input = pbr_input_from_standard_material(in, is_front);
+
+    // Map cell `(x, y)` of the chunk is at `(x, -y)` from its origin on the XZ plane.
+    let local = in.world_position.xz - terrain.origin;
+    let splat_uv = (vec2<f32>(local.x, -local.y) + 0.5) / terrain.size;
+    let splat = textureSample(splat_texture, splat_sampler, splat_uv);
+
+    var projection = pow(abs(normalize(in.world_normal)), vec3<f32>(4.0));
+    projection /= projection.x + projection.y + projection.z;
+
+    let position = in.world_position.xyz;
+    let layers = triplanar(sand_texture, position, projection) * splat.r
+        + triplanar(grass_texture, position, projection) * splat.g
+        + triplanar(rock_texture, position, projection) * splat.b
+        + triplanar(snow_texture, position, projection) * splat.a;
+
+    let vertex_color = pbr_input.material.base_color;
+    pbr_input.material.base_color = vec4<f32>(
+        mix(layers.rgb, vertex_color.rgb, terrain.vertex_color_mix),
+        vertex_color.a,
+    );
+    pbr_input.material.base_color = alpha_discard(pbr_input.material, pbr_input.material.base_color);
+
+    var out: FragmentOutput;
+    out.color = apply_pbr_lighting(pbr_input);
+    out.color = main_pass_post_lighting_processing(pbr_input, out.color);
+    return out;
+}
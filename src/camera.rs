@@ -1,12 +1,136 @@
-use bevy::prelude::*;
+use bevy::{
+    input::mouse::{MouseMotion, MouseWheel},
+    prelude::*,
+    transform::TransformSystem,
+    window::{CursorGrabMode, PrimaryWindow},
+};
 use bevy_atmosphere::plugin::AtmosphereCamera;
-use bevy_third_person_camera::{ThirdPersonCamera, Zoom};
+use bevy_rapier3d::prelude::*;
+use bevy_third_person_camera::{ThirdPersonCamera, ThirdPersonCameraTarget, Zoom};
+
+use crate::player::Player;
 
 pub struct CameraPlugin;
 
 impl Plugin for CameraPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, spawn_camera);
+        app.add_state::<CameraMode>()
+            .init_resource::<CameraSettings>()
+            .register_type::<CameraSettings>()
+            .add_systems(Startup, spawn_camera)
+            .add_systems(Update, cycle_camera_mode)
+            .add_systems(Update, orbit_zoom.run_if(in_state(CameraMode::ThirdPerson)))
+            .add_systems(OnEnter(CameraMode::ThirdPerson), enter_third_person)
+            .add_systems(OnExit(CameraMode::ThirdPerson), exit_third_person)
+            .add_systems(OnEnter(CameraMode::FirstPerson), hide_player)
+            .add_systems(OnExit(CameraMode::FirstPerson), show_player)
+            .add_systems(
+                Update,
+                (
+                    (mouse_look, first_person_follow)
+                        .chain()
+                        .run_if(in_state(CameraMode::FirstPerson)),
+                    (mouse_look, free_fly_movement)
+                        .chain()
+                        .run_if(in_state(CameraMode::FreeFly)),
+                ),
+            )
+            .add_systems(
+                PostUpdate,
+                orbit_collision
+                    .run_if(in_state(CameraMode::ThirdPerson))
+                    .after(PhysicsSet::Writeback)
+                    .before(TransformSystem::TransformPropagate),
+            );
+    }
+}
+
+#[derive(States, Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CameraMode {
+    FirstPerson,
+    /// Orbit camera around the player provided by `bevy_third_person_camera`.
+    #[default]
+    ThirdPerson,
+    /// Detached noclip camera for inspecting the world.
+    FreeFly,
+}
+
+impl CameraMode {
+    fn next(self) -> Self {
+        match self {
+            CameraMode::FirstPerson => CameraMode::ThirdPerson,
+            CameraMode::ThirdPerson => CameraMode::FreeFly,
+            CameraMode::FreeFly => CameraMode::FirstPerson,
+        }
+    }
+}
+
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+pub struct CameraSettings {
+    pub cycle_key: KeyCode,
+    /// Radians of rotation per pixel of mouse movement.
+    pub look_sensitivity: f32,
+    /// Height of the first person camera above the player's center.
+    pub eye_height: f32,
+    pub free_fly_speed: f32,
+    pub free_fly_boost: f32,
+    /// Radius of the sphere swept from the player to the orbit camera.
+    pub collision_radius: f32,
+    /// Distance kept between the orbit camera and whatever blocks its view.
+    pub collision_margin: f32,
+    /// How quickly the orbit camera moves back out once the view is clear.
+    pub collision_ease_out: f32,
+}
+
+impl Default for CameraSettings {
+    fn default() -> Self {
+        Self {
+            cycle_key: KeyCode::F5,
+            look_sensitivity: 0.002,
+            eye_height: 0.4,
+            free_fly_speed: 10.0,
+            free_fly_boost: 4.0,
+            collision_radius: 0.3,
+            collision_margin: 0.1,
+            collision_ease_out: 4.0,
+        }
+    }
+}
+
+/// How far the orbit camera wants to be from its target and how far it currently is.
+#[derive(Component)]
+struct OrbitDistance {
+    /// Zoom radius picked with the scroll wheel, tracked here since the third person
+    /// plugin keeps its own private.
+    zoom: f32,
+    /// Distance collisions last left the camera at.
+    current: Option<f32>,
+}
+
+impl OrbitDistance {
+    fn new(camera: &ThirdPersonCamera) -> Self {
+        Self {
+            // Where the plugin starts its zoom too.
+            zoom: (camera.zoom.min + camera.zoom.max) / 2.0,
+            current: None,
+        }
+    }
+}
+
+/// Yaw and pitch of a camera driven directly by the mouse.
+#[derive(Component, Default)]
+struct LookAngles {
+    yaw: f32,
+    pitch: f32,
+}
+
+fn third_person_camera() -> ThirdPersonCamera {
+    ThirdPersonCamera {
+        zoom: Zoom::new(5.0, 50.0),
+        mouse_sensitivity: 2.4,
+        cursor_lock_key: KeyCode::Escape,
+        ..Default::default()
     }
 }
 
@@ -16,14 +140,197 @@ fn spawn_camera(mut commands: Commands) {
         ..Default::default()
     };
 
-    commands.spawn((
-        camera,
-        ThirdPersonCamera {
-            zoom: Zoom::new(5.0, 50.0),
-            mouse_sensitivity: 2.4,
-            cursor_lock_key: KeyCode::Escape,
-            ..Default::default()
-        },
-        AtmosphereCamera::default(),
-    ));
+    commands.spawn((camera, LookAngles::default(), AtmosphereCamera::default()));
+}
+
+fn cycle_camera_mode(
+    keys: Res<Input<KeyCode>>,
+    settings: Res<CameraSettings>,
+    mode: Res<State<CameraMode>>,
+    mut next_mode: ResMut<NextState<CameraMode>>,
+) {
+    if keys.just_pressed(settings.cycle_key) {
+        next_mode.set(mode.get().next());
+    }
+}
+
+fn enter_third_person(mut commands: Commands, camera_query: Query<Entity, With<Camera3d>>) {
+    for entity in camera_query.iter() {
+        let camera = third_person_camera();
+        let orbit_distance = OrbitDistance::new(&camera);
+        commands.entity(entity).insert((camera, orbit_distance));
+    }
+}
+
+fn exit_third_person(
+    mut commands: Commands,
+    mut window_query: Query<&mut Window, With<PrimaryWindow>>,
+    mut camera_query: Query<(Entity, &Transform, &mut LookAngles), With<Camera3d>>,
+) {
+    for (entity, transform, mut angles) in camera_query.iter_mut() {
+        commands
+            .entity(entity)
+            .remove::<(ThirdPersonCamera, OrbitDistance)>();
+
+        let (yaw, pitch, _) = transform.rotation.to_euler(EulerRot::YXZ);
+        angles.yaw = yaw;
+        angles.pitch = pitch;
+    }
+
+    // The orbit camera manages the cursor itself, the other modes always need it grabbed.
+    if let Ok(mut window) = window_query.get_single_mut() {
+        window.cursor.grab_mode = CursorGrabMode::Locked;
+        window.cursor.visible = false;
+    }
+}
+
+fn hide_player(mut player_query: Query<&mut Visibility, With<Player>>) {
+    for mut visibility in player_query.iter_mut() {
+        *visibility = Visibility::Hidden;
+    }
+}
+
+fn show_player(mut player_query: Query<&mut Visibility, With<Player>>) {
+    for mut visibility in player_query.iter_mut() {
+        *visibility = Visibility::Inherited;
+    }
+}
+
+fn mouse_look(
+    settings: Res<CameraSettings>,
+    mut motion: EventReader<MouseMotion>,
+    mut camera_query: Query<(&mut Transform, &mut LookAngles), With<Camera3d>>,
+) {
+    let delta: Vec2 = motion.read().map(|event| event.delta).sum();
+
+    for (mut transform, mut angles) in camera_query.iter_mut() {
+        angles.yaw -= delta.x * settings.look_sensitivity;
+        angles.pitch = (angles.pitch - delta.y * settings.look_sensitivity).clamp(
+            -std::f32::consts::FRAC_PI_2 + 0.01,
+            std::f32::consts::FRAC_PI_2 - 0.01,
+        );
+        transform.rotation = Quat::from_euler(EulerRot::YXZ, angles.yaw, angles.pitch, 0.0);
+    }
+}
+
+fn first_person_follow(
+    settings: Res<CameraSettings>,
+    player_query: Query<&Transform, With<Player>>,
+    mut camera_query: Query<&mut Transform, (With<Camera3d>, Without<Player>)>,
+) {
+    let Ok(player) = player_query.get_single() else {
+        return;
+    };
+
+    for mut transform in camera_query.iter_mut() {
+        transform.translation = player.translation + Vec3::Y * settings.eye_height;
+    }
+}
+
+fn free_fly_movement(
+    keys: Res<Input<KeyCode>>,
+    time: Res<Time>,
+    settings: Res<CameraSettings>,
+    mut camera_query: Query<&mut Transform, With<Camera3d>>,
+) {
+    for mut transform in camera_query.iter_mut() {
+        let mut direction = Vec3::ZERO;
+
+        if keys.pressed(KeyCode::W) {
+            direction += transform.forward();
+        }
+        if keys.pressed(KeyCode::A) {
+            direction += transform.left();
+        }
+        if keys.pressed(KeyCode::S) {
+            direction += transform.back();
+        }
+        if keys.pressed(KeyCode::D) {
+            direction += transform.right();
+        }
+        if keys.pressed(KeyCode::Space) {
+            direction += Vec3::Y;
+        }
+        if keys.pressed(KeyCode::ControlLeft) {
+            direction -= Vec3::Y;
+        }
+
+        let mut speed = settings.free_fly_speed;
+        if keys.pressed(KeyCode::ShiftLeft) {
+            speed *= settings.free_fly_boost;
+        }
+
+        transform.translation += direction.normalize_or_zero() * speed * time.delta_seconds();
+    }
+}
+
+/// Zooms the orbit camera in and out with the scroll wheel, by the same steps as the
+/// third person plugin.
+fn orbit_zoom(
+    mut scroll: EventReader<MouseWheel>,
+    mut camera_query: Query<(&ThirdPersonCamera, &mut OrbitDistance)>,
+) {
+    let scroll: f32 = scroll.read().map(|event| event.y).sum();
+    if scroll == 0.0 {
+        return;
+    }
+
+    for (camera, mut orbit_distance) in camera_query.iter_mut() {
+        if !camera.zoom_enabled {
+            continue;
+        }
+        let zoom = orbit_distance.zoom * (1.0 - scroll * 0.1 * camera.zoom_sensitivity);
+        orbit_distance.zoom = zoom.clamp(camera.zoom.min, camera.zoom.max);
+    }
+}
+
+/// Sweeps a sphere from the player to the orbit camera and pulls the camera in front of
+/// anything in the way. The camera snaps in immediately so it never clips, and eases back
+/// out once the view clears so it doesn't jitter around edges.
+#[allow(clippy::type_complexity)]
+fn orbit_collision(
+    rapier_context: Res<RapierContext>,
+    settings: Res<CameraSettings>,
+    time: Res<Time>,
+    target_query: Query<(Entity, &Transform), With<ThirdPersonCameraTarget>>,
+    mut camera_query: Query<
+        (&mut Transform, &mut OrbitDistance),
+        (With<ThirdPersonCamera>, Without<ThirdPersonCameraTarget>),
+    >,
+) {
+    let Ok((target_entity, target)) = target_query.get_single() else {
+        return;
+    };
+
+    for (mut transform, mut orbit_distance) in camera_query.iter_mut() {
+        let offset = transform.translation - target.translation;
+        let measured = offset.length();
+        if measured <= f32::EPSILON {
+            continue;
+        }
+        let direction = offset / measured;
+
+        let desired = orbit_distance.zoom;
+
+        let allowed = rapier_context
+            .cast_shape(
+                target.translation,
+                Quat::IDENTITY,
+                direction,
+                &Collider::ball(settings.collision_radius),
+                desired,
+                QueryFilter::default()
+                    .exclude_rigid_body(target_entity)
+                    .exclude_sensors(),
+            )
+            .map(|(_, toi)| (toi.toi - settings.collision_margin).max(0.0))
+            .unwrap_or(desired);
+
+        let current = orbit_distance.current.unwrap_or(allowed);
+        let t = 1.0 - (-settings.collision_ease_out * time.delta_seconds()).exp();
+        let distance = (current + (allowed - current) * t).min(allowed);
+        orbit_distance.current = Some(distance);
+
+        transform.translation = target.translation + direction * distance;
+    }
 }
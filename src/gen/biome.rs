@@ -0,0 +1,50 @@
+use bevy::reflect::Reflect;
+
+/// Degrees lost between sea level and the highest possible terrain.
+pub const LAPSE_RATE: f32 = 25.0;
+
+/// Sea level temperature averaged over a year, used for anything that must not change
+/// with the seasons like where trees grow.
+pub const MEAN_TEMPERATURE: f32 = 10.0;
+
+#[derive(Reflect, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Biome {
+    Ocean,
+    Beach,
+    Desert,
+    #[default]
+    Grassland,
+    Forest,
+    Taiga,
+    Tundra,
+    Snow,
+}
+
+impl Biome {
+    /// Picks a biome from normalized `height` and `sea_level`, `temperature` in degrees
+    /// and `moisture` in `0.0..=1.0`.
+    pub fn classify(height: f64, sea_level: f64, temperature: f32, moisture: f32) -> Self {
+        if height <= sea_level {
+            return Biome::Ocean;
+        }
+        if height <= sea_level + 0.03 && temperature > 0.0 {
+            return Biome::Beach;
+        }
+
+        match temperature {
+            t if t < -10.0 => Biome::Snow,
+            t if t < 0.0 => Biome::Tundra,
+            t if t < 8.0 && moisture > 0.4 => Biome::Taiga,
+            t if t < 8.0 => Biome::Tundra,
+            t if t > 25.0 && moisture < 0.3 => Biome::Desert,
+            _ if moisture > 0.5 => Biome::Forest,
+            _ => Biome::Grassland,
+        }
+    }
+}
+
+/// Temperature at a normalized `height`, given the temperature at sea level.
+pub fn temperature_at(sea_level_temperature: f32, height: f64, sea_level: f64) -> f32 {
+    let altitude = ((height - sea_level) / (1.0 - sea_level)).clamp(0.0, 1.0) as f32;
+    sea_level_temperature - altitude * LAPSE_RATE
+}
@@ -0,0 +1,335 @@
+use bevy::{ecs::system::SystemParam, prelude::*, utils::HashMap};
+
+use super::{
+    biome::Biome,
+    diff::ChunkDiffs,
+    river::{self, RiverMaterial, Rivers},
+    roads::{self, Roads},
+    scatter::{self, Placement},
+    splat::{TerrainLayers, TerrainMaterial},
+    structures::{self, StructureAssets, Structures},
+    Generated, Noise, NoiseConfig, TerrainTint,
+};
+
+/// Most chunks generated in a single frame, so walking into new terrain doesn't stall.
+const CHUNKS_PER_FRAME: usize = 2;
+/// Most roads searched for in a single frame, each is an A* over hundreds of cells.
+const ROAD_SEARCHES_PER_FRAME: usize = 2;
+
+/// Position of a chunk in the chunk grid.
+///
+/// Chunk `(x, y)` covers global cells `x * chunk_size..=(x + 1) * chunk_size` and the
+/// same for `y`. Global cell `(x, y)` is at world `(x, -y)` on the XZ plane.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ChunkCoord(pub IVec2);
+
+impl ChunkCoord {
+    /// Chunk containing a world position on the XZ plane.
+    pub fn from_world(position: Vec2, chunk_size: usize) -> Self {
+        let size = chunk_size as f32;
+        Self(IVec2::new(
+            (position.x / size).floor() as i32,
+            (-position.y / size).floor() as i32,
+        ))
+    }
+
+    /// Global cell of the chunk's first sample.
+    pub fn origin(self, chunk_size: usize) -> IVec2 {
+        self.0 * chunk_size as i32
+    }
+
+    /// Number of chunks between the two along the furthest axis.
+    pub fn distance(self, other: ChunkCoord) -> i32 {
+        (self.0 - other.0).abs().max_element()
+    }
+}
+
+/// Systems that load, unload and rebuild chunks, anything waiting on [`ChunkLoaded`] should
+/// run after it.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ChunkSet;
+
+/// Keeps the chunks within this many cells of the entity loaded, wherever the camera is.
+///
+/// Loading them goes ahead of the chunks around the camera, for things that wait on the
+/// terrain in a particular place.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct ChunkAnchor(pub u32);
+
+/// Sent once a chunk's mesh and collider have been spawned and its map is in [`Terrain`].
+#[derive(Event)]
+pub struct ChunkLoaded(pub ChunkCoord);
+
+/// Sent after a loaded chunk's heights were edited and its mesh and collider rebuilt.
+#[derive(Event)]
+pub struct ChunkModified(pub ChunkCoord);
+
+/// Sent when a chunk leaves the view distance and is despawned.
+#[derive(Event)]
+pub struct ChunkUnloaded(pub ChunkCoord);
+
+pub struct LoadedChunk {
+    pub entity: Entity,
+    pub map: Noise<Generated>,
+    /// Everything scattered over the chunk, positioned relative to the chunk entity.
+    pub scatter: Vec<Placement>,
+    /// Layer weights of the chunk's material, rebuilt along with the mesh.
+    pub splat: Handle<Image>,
+}
+
+/// The chunks currently loaded around the camera.
+#[derive(Resource, Default)]
+pub struct Terrain {
+    pub(super) chunk_size: usize,
+    pub(super) chunks: HashMap<ChunkCoord, LoadedChunk>,
+}
+
+impl Terrain {
+    pub fn chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+
+    pub fn get(&self, coord: ChunkCoord) -> Option<&LoadedChunk> {
+        self.chunks.get(&coord)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&ChunkCoord, &LoadedChunk)> {
+        self.chunks.iter()
+    }
+
+    /// Loaded chunk containing a world position on the XZ plane.
+    pub fn chunk_at(&self, position: Vec2) -> Option<&LoadedChunk> {
+        if self.chunk_size == 0 {
+            return None;
+        }
+        self.get(ChunkCoord::from_world(position, self.chunk_size))
+    }
+
+    /// World height of the terrain at a world position on the XZ plane.
+    pub fn height_at_world(&self, position: Vec2) -> Option<f32> {
+        self.chunk_at(position)?.map.height_at_world(position)
+    }
+
+    pub fn biome_at_world(&self, position: Vec2, sea_level_temperature: f32) -> Option<Biome> {
+        let map = &self.chunk_at(position)?.map;
+        let (x, y) = map.world_to_map(position)?;
+        Some(map.biome_at(x, y, sea_level_temperature))
+    }
+}
+
+/// Assets every streamed in chunk is built from.
+#[derive(SystemParam)]
+pub(super) struct ChunkAssets<'w> {
+    meshes: ResMut<'w, Assets<Mesh>>,
+    images: ResMut<'w, Assets<Image>>,
+    materials: ResMut<'w, Assets<TerrainMaterial>>,
+    layers: Res<'w, TerrainLayers>,
+    river_material: Res<'w, RiverMaterial>,
+    structures: Res<'w, StructureAssets>,
+}
+
+/// Drops every chunk when the config changes so they stream back in regenerated.
+pub(super) fn reload_chunks(
+    mut commands: Commands,
+    config: Res<NoiseConfig>,
+    mut terrain: ResMut<Terrain>,
+    mut rivers: ResMut<Rivers>,
+    mut structures: ResMut<Structures>,
+    mut roads: ResMut<Roads>,
+    mut unloaded_events: EventWriter<ChunkUnloaded>,
+) {
+    if !config.is_changed() {
+        return;
+    }
+
+    rivers.clear();
+    structures.clear();
+    roads.clear();
+    for (coord, chunk) in terrain.chunks.drain() {
+        commands.entity(chunk.entity).despawn_recursive();
+        unloaded_events.send(ChunkUnloaded(coord));
+    }
+    terrain.chunk_size = config.chunk_size;
+}
+
+/// Recolors the loaded chunks when the tint changes, their shape and everything on them
+/// stays as it is.
+#[allow(clippy::too_many_arguments)]
+pub(super) fn retint_chunks(
+    mut commands: Commands,
+    config: Res<NoiseConfig>,
+    tint: Res<TerrainTint>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut images: ResMut<Assets<Image>>,
+    mut materials: ResMut<Assets<TerrainMaterial>>,
+    mut terrain: ResMut<Terrain>,
+    material_query: Query<&Handle<TerrainMaterial>>,
+) {
+    // A new config regenerates the chunks with the tint anyway.
+    if !tint.is_changed() || config.is_changed() {
+        return;
+    }
+
+    for chunk in terrain.chunks.values_mut() {
+        chunk.map.tint = *tint;
+        images.insert(&chunk.splat, chunk.map.splat_map());
+        // Touching the material makes it bind the new splat map.
+        if let Ok(material) = material_query.get(chunk.entity) {
+            materials.get_mut(material);
+        }
+
+        let mesh_data = chunk.map.build_mesh();
+        commands
+            .entity(chunk.entity)
+            .insert(meshes.add(mesh_data.create_mesh()));
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(super) fn stream_chunks(
+    mut commands: Commands,
+    mut assets: ChunkAssets,
+    config: Res<NoiseConfig>,
+    tint: Res<TerrainTint>,
+    diffs: Res<ChunkDiffs>,
+    mut terrain: ResMut<Terrain>,
+    mut rivers: ResMut<Rivers>,
+    mut structures: ResMut<Structures>,
+    mut roads: ResMut<Roads>,
+    focus_query: Query<&GlobalTransform, With<Camera3d>>,
+    anchor_query: Query<(&GlobalTransform, &ChunkAnchor)>,
+    mut loaded_events: EventWriter<ChunkLoaded>,
+    mut unloaded_events: EventWriter<ChunkUnloaded>,
+) {
+    let Ok(focus) = focus_query.get_single() else {
+        return;
+    };
+
+    let center = ChunkCoord::from_world(focus.translation().xz(), config.chunk_size);
+    let view_distance = config.view_distance as i32;
+    let anchored: Vec<(IVec2, IVec2)> = anchor_query
+        .iter()
+        .map(|(transform, anchor)| {
+            let position = transform.translation().xz();
+            let reach = Vec2::splat(anchor.0 as f32);
+            let a = ChunkCoord::from_world(position - reach, config.chunk_size).0;
+            let b = ChunkCoord::from_world(position + reach, config.chunk_size).0;
+            (a.min(b), a.max(b))
+        })
+        .collect();
+    let is_anchored = |coord: &ChunkCoord| {
+        anchored
+            .iter()
+            .any(|(min, max)| coord.0.cmpge(*min).all() && coord.0.cmple(*max).all())
+    };
+
+    // One extra ring is kept so walking along a chunk border doesn't reload it constantly.
+    let far: Vec<ChunkCoord> = terrain
+        .chunks
+        .keys()
+        .filter(|coord| coord.distance(center) > view_distance + 1 && !is_anchored(coord))
+        .copied()
+        .collect();
+    for coord in far {
+        if let Some(chunk) = terrain.chunks.remove(&coord) {
+            commands.entity(chunk.entity).despawn_recursive();
+            unloaded_events.send(ChunkUnloaded(coord));
+        }
+    }
+
+    let view = (-view_distance..=view_distance)
+        .flat_map(|y| (-view_distance..=view_distance).map(move |x| IVec2::new(x, y)))
+        .map(|offset| ChunkCoord(center.0 + offset));
+    let anchors = anchored.iter().flat_map(|&(min, max)| {
+        (min.y..=max.y)
+            .flat_map(move |y| (min.x..=max.x).map(move |x| ChunkCoord(IVec2::new(x, y))))
+    });
+    let mut missing: Vec<ChunkCoord> = view
+        .chain(anchors)
+        .filter(|coord| !terrain.chunks.contains_key(coord))
+        .collect();
+    missing.sort_by_key(|coord| {
+        let distance = (coord.0 - center.0).length_squared();
+        (!is_anchored(coord), distance, coord.0.x, coord.0.y)
+    });
+    missing.dedup();
+
+    let mut road_searches = ROAD_SEARCHES_PER_FRAME;
+    for coord in missing.into_iter().take(CHUNKS_PER_FRAME) {
+        // A chunk that needs more roads than are left to find this frame waits for the
+        // next, keeping the ones found so far.
+        let Some(nearby_roads) = roads.near_chunk(
+            &config,
+            &mut structures,
+            &mut rivers,
+            coord,
+            &mut road_searches,
+        ) else {
+            break;
+        };
+        // Generated with an apron of one cell around it so the normals on its border
+        // match the neighbors'.
+        let mut map = Noise::from(&*config)
+            .with_tint(*tint)
+            .with_origin(coord.origin(config.chunk_size) - IVec2::ONE)
+            .with_size(config.chunk_size + 3, config.chunk_size + 3)
+            .generate_map();
+        let nearby_structures = structures.near_chunk(&config, &mut rivers, coord);
+        let nearby_rivers = rivers.near_chunk(&config, coord);
+        river::carve(&mut map, &nearby_rivers);
+        structures::flatten(&mut map, &nearby_structures);
+        roads::pave(&mut map, &nearby_roads);
+        let mut map = map.split_apron();
+        let river_mesh = river::ribbon_mesh(&map, &nearby_rivers);
+        // Scattering the untouched map keeps placement indices stable however it's edited.
+        let mut scatter = scatter::scatter_chunk(&map, coord);
+        structures::clear_footprints(&mut scatter, &map, &nearby_structures);
+        roads::clear_roads(&mut scatter, &map);
+        if let Some(diff) = diffs.get(coord) {
+            diff.apply(&mut map);
+            scatter::settle(&mut scatter, &map);
+        }
+        diffs.apply_apron(&mut map, config.chunk_size);
+        let mesh_data = map.build_mesh();
+        let splat = assets.images.add(map.splat_map());
+        let material = assets.layers.material(&map, splat.clone());
+
+        let entity = commands
+            .spawn((
+                MaterialMeshBundle {
+                    mesh: assets.meshes.add(mesh_data.create_mesh()),
+                    material: assets.materials.add(material),
+                    transform: Transform::from_translation(map.world_origin()),
+                    ..Default::default()
+                },
+                mesh_data.create_collider(),
+                coord,
+                Name::new(format!("Chunk ({}, {})", coord.0.x, coord.0.y)),
+            ))
+            .id();
+        commands.entity(entity).with_children(|parent| {
+            if let Some(river_mesh) = river_mesh {
+                parent.spawn((
+                    PbrBundle {
+                        mesh: assets.meshes.add(river_mesh),
+                        material: assets.river_material.0.clone(),
+                        ..Default::default()
+                    },
+                    Name::new("Rivers"),
+                ));
+            }
+            structures::spawn_structures(parent, &map, &nearby_structures, &assets.structures);
+        });
+
+        terrain.chunks.insert(
+            coord,
+            LoadedChunk {
+                entity,
+                map,
+                scatter,
+                splat,
+            },
+        );
+        loaded_events.send(ChunkLoaded(coord));
+    }
+}
@@ -0,0 +1,193 @@
+//! Continents and oceans laid out by plates, biasing the local noise in
+//! [`generate_map`](super::Noise::generate_map).
+//!
+//! Plates sit on a jittered grid, each either continental or oceanic and drifting in its
+//! own direction. Land rises where continental plates are and mountain ranges grow where
+//! two plates push into each other. The layer is smooth over hundreds of cells, so it is
+//! only evaluated on a coarse lattice and interpolated between.
+
+use bevy::math::{DVec2, IVec2};
+use noise::{NoiseFn, Perlin};
+use rand::Rng;
+
+use super::scatter::grid_rng;
+
+/// Global cells between two lattice samples of the layer.
+const LATTICE_STEP: i32 = 32;
+const PLATE_SALT: u64 = 0x706c_6174;
+const CONTINENTAL_CHANCE: f64 = 0.45;
+/// Fraction of the plate size over which neighboring plates blend into each other.
+const BOUNDARY_WIDTH: f64 = 0.2;
+/// Fraction of the plate size coastlines are pushed around by to break up the grid.
+const WARP_STRENGTH: f64 = 0.3;
+/// How much rougher the local noise gets on the highest mountain ranges.
+const MOUNTAIN_DETAIL: f64 = 1.5;
+
+/// What the continental layer does to the terrain at a point.
+#[derive(Clone, Copy, Debug, Default)]
+struct ContinentSample {
+    /// Around `1.0` in the middle of continents, around `-1.0` in the deep ocean.
+    elevation: f64,
+    /// `0.0..=1.0`, highest along colliding plate boundaries.
+    uplift: f64,
+}
+
+impl ContinentSample {
+    fn lerp(self, other: Self, t: f64) -> Self {
+        Self {
+            elevation: self.elevation + (other.elevation - self.elevation) * t,
+            uplift: self.uplift + (other.uplift - self.uplift) * t,
+        }
+    }
+}
+
+struct Plate {
+    center: DVec2,
+    elevation: f64,
+    drift: DVec2,
+}
+
+/// The continental layer of a seed.
+#[derive(Clone, Copy, Debug)]
+pub struct Continents {
+    seed: u32,
+    /// Rough size of a plate in global cells.
+    plate_size: f64,
+    /// Normalized height continents rise and oceans sink by, `0.0` turns the layer off.
+    strength: f64,
+    warp: Perlin,
+}
+
+impl Continents {
+    pub fn new(seed: u32, plate_size: f64, strength: f64) -> Self {
+        Self {
+            seed,
+            plate_size: plate_size.max(LATTICE_STEP as f64),
+            strength,
+            warp: Perlin::new(seed.wrapping_add(4)),
+        }
+    }
+
+    fn plate(&self, cell: IVec2) -> Plate {
+        let mut rng = grid_rng(self.seed, cell, PLATE_SALT);
+        let jitter = DVec2::new(rng.gen_range(0.15..0.85), rng.gen_range(0.15..0.85));
+        let elevation = match rng.gen_bool(CONTINENTAL_CHANCE) {
+            true => rng.gen_range(0.3..1.0),
+            false => rng.gen_range(-1.0..-0.4),
+        };
+        let angle = rng.gen_range(0.0..std::f64::consts::TAU);
+
+        Plate {
+            center: (cell.as_dvec2() + jitter) * self.plate_size,
+            elevation,
+            drift: DVec2::from_angle(angle),
+        }
+    }
+
+    fn sample(&self, global: DVec2) -> ContinentSample {
+        let frequency = global / (self.plate_size * 0.5);
+        let warp = DVec2::new(
+            self.warp.get(frequency.to_array()),
+            self.warp.get((frequency + 100.0).to_array()),
+        );
+        let point = global + warp * self.plate_size * WARP_STRENGTH;
+
+        // The two closest plates decide the point; jitter keeps them within one cell.
+        let cell = (point / self.plate_size).floor().as_ivec2();
+        let mut plates: Vec<Plate> = (-1..=1)
+            .flat_map(|y| (-1..=1).map(move |x| cell + IVec2::new(x, y)))
+            .map(|cell| self.plate(cell))
+            .collect();
+        plates.sort_by(|a, b| {
+            let a = a.center.distance_squared(point);
+            let b = b.center.distance_squared(point);
+            a.total_cmp(&b)
+        });
+        let (near, far) = (&plates[0], &plates[1]);
+
+        // Distance to the boundary between the two plates, along the line joining them.
+        let normal = (far.center - near.center).normalize_or_zero();
+        let midpoint = (near.center + far.center) / 2.0;
+        let distance = (midpoint - point).dot(normal);
+        let boundary = (1.0 - distance / (self.plate_size * BOUNDARY_WIDTH)).clamp(0.0, 1.0);
+        let boundary = boundary * boundary * (3.0 - 2.0 * boundary);
+
+        // Halfway between both plates right at the boundary.
+        let elevation = near.elevation + (far.elevation - near.elevation) * boundary * 0.5;
+        let convergence = (near.drift - far.drift).dot(normal);
+        // Diverging plates pull a rift open instead.
+        let uplift = convergence.clamp(-1.0, 1.0) * boundary;
+
+        ContinentSample {
+            elevation: elevation + uplift.min(0.0) * 0.5,
+            uplift: uplift.max(0.0),
+        }
+    }
+
+    /// Evaluates the layer on the lattice around a map so its cells can be interpolated.
+    pub(super) fn layer(
+        &self,
+        origin: IVec2,
+        step: usize,
+        width: usize,
+        height: usize,
+    ) -> ContinentLayer {
+        let last = origin + IVec2::new(width as i32 - 1, height as i32 - 1) * step as i32;
+        let first = IVec2::new(
+            origin.x.div_euclid(LATTICE_STEP),
+            origin.y.div_euclid(LATTICE_STEP),
+        );
+        let size = IVec2::new(
+            last.x.div_euclid(LATTICE_STEP),
+            last.y.div_euclid(LATTICE_STEP),
+        ) - first
+            + IVec2::splat(2);
+
+        let samples = match self.strength > 0.0 {
+            true => (0..size.y)
+                .flat_map(|y| (0..size.x).map(move |x| IVec2::new(x, y)))
+                .map(|lattice| self.sample(((first + lattice) * LATTICE_STEP).as_dvec2()))
+                .collect(),
+            false => Vec::new(),
+        };
+
+        ContinentLayer {
+            first,
+            width: size.x as usize,
+            samples,
+            strength: self.strength,
+        }
+    }
+}
+
+/// The continental layer sampled on the lattice around one map.
+pub(super) struct ContinentLayer {
+    first: IVec2,
+    width: usize,
+    samples: Vec<ContinentSample>,
+    strength: f64,
+}
+
+impl ContinentLayer {
+    fn sample_at(&self, global: DVec2) -> ContinentSample {
+        let lattice = global / LATTICE_STEP as f64 - self.first.as_dvec2();
+        let (x0, y0) = (lattice.x.floor() as usize, lattice.y.floor() as usize);
+        let (tx, ty) = (lattice.x.fract(), lattice.y.fract());
+
+        let at = |x: usize, y: usize| self.samples[x + y * self.width];
+        let top = at(x0, y0).lerp(at(x0 + 1, y0), tx);
+        let bottom = at(x0, y0 + 1).lerp(at(x0 + 1, y0 + 1), tx);
+        top.lerp(bottom, ty)
+    }
+
+    /// Raises or sinks a normalized local height by the continent at a global cell.
+    pub fn apply(&self, global: DVec2, height: f64) -> f64 {
+        if self.samples.is_empty() {
+            return height;
+        }
+        let sample = self.sample_at(global);
+        let detail = (height - 0.5) * (1.0 + sample.uplift * MOUNTAIN_DETAIL);
+        let rise = (sample.elevation + sample.uplift) * self.strength;
+        (0.5 + detail + rise).clamp(0.0, 1.0)
+    }
+}
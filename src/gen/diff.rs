@@ -0,0 +1,124 @@
+use std::collections::BTreeMap;
+
+use bevy::{prelude::*, utils::HashMap};
+use serde::{Deserialize, Serialize};
+
+use crate::logic::gathering::{Depleted, ResourceNode};
+
+use super::{chunk::ChunkCoord, Generated, Noise};
+
+/// Changes made to a chunk on top of what its seed generates.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ChunkDiff {
+    /// Scattered nodes that were gathered from, by placement index.
+    #[serde(default)]
+    pub nodes: BTreeMap<usize, NodeDiff>,
+    /// Normalized height added to the generated one, by map cell index.
+    #[serde(default)]
+    pub heights: BTreeMap<usize, f64>,
+}
+
+/// What is left of a scattered node that was gathered from.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct NodeDiff {
+    pub harvests_left: u32,
+    /// Seconds until the node grows back, `None` unless it is depleted.
+    #[serde(default)]
+    pub respawn_in: Option<f32>,
+}
+
+impl ChunkDiff {
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty() && self.heights.is_empty()
+    }
+
+    /// Applies the height changes to a freshly generated map of the chunk.
+    pub fn apply(&self, map: &mut Noise<Generated>) {
+        for (&index, offset) in &self.heights {
+            if let Some(height) = map.noise_map.get_mut(index) {
+                *height += offset;
+            }
+        }
+    }
+}
+
+/// Every chunk's diff, kept while the chunk itself is unloaded.
+#[derive(Resource, Default, Clone)]
+pub struct ChunkDiffs(pub HashMap<ChunkCoord, ChunkDiff>);
+
+impl ChunkDiffs {
+    pub fn get(&self, coord: ChunkCoord) -> Option<&ChunkDiff> {
+        self.0.get(&coord)
+    }
+
+    /// Diff of a chunk, created empty if it has none yet.
+    pub fn entry(&mut self, coord: ChunkCoord) -> &mut ChunkDiff {
+        self.0.entry(coord).or_default()
+    }
+
+    /// Applies the height changes of neighboring chunks to a map's apron.
+    pub fn apply_apron(&self, map: &mut Noise<Generated>, chunk_size: usize) {
+        let size = chunk_size as i32;
+        let (width, height) = (map.width as i32, map.height as i32);
+        for y in -1..=height {
+            for x in -1..=width {
+                if (0..width).contains(&x) && (0..height).contains(&y) {
+                    continue;
+                }
+                let Some(apron_index) = map.apron_index(x, y) else {
+                    continue;
+                };
+
+                let cell = map.origin + IVec2::new(x, y);
+                let owner =
+                    ChunkCoord(IVec2::new(cell.x.div_euclid(size), cell.y.div_euclid(size)));
+                let local = IVec2::new(cell.x.rem_euclid(size), cell.y.rem_euclid(size));
+                let index = (local.x + local.y * (size + 1)) as usize;
+                if let Some(offset) = self.get(owner).and_then(|diff| diff.heights.get(&index)) {
+                    map.apron[apron_index] += offset;
+                }
+            }
+        }
+    }
+
+    /// Drops the chunk's diff if nothing in it differs from the generated chunk anymore.
+    pub fn prune(&mut self, coord: ChunkCoord) {
+        if self.0.get(&coord).is_some_and(ChunkDiff::is_empty) {
+            self.0.remove(&coord);
+        }
+    }
+}
+
+/// Which chunk placement a spawned object came from.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct ScatteredFrom {
+    pub chunk: ChunkCoord,
+    pub index: usize,
+}
+
+/// Keeps the diffs up to date with gathered nodes, including how far along depleted ones
+/// are in growing back.
+#[allow(clippy::type_complexity)]
+pub(super) fn record_node_diffs(
+    mut diffs: ResMut<ChunkDiffs>,
+    node_query: Query<
+        (&ScatteredFrom, &ResourceNode, Option<&Depleted>),
+        Or<(Changed<ResourceNode>, Changed<Depleted>)>,
+    >,
+) {
+    for (from, node, depleted) in node_query.iter() {
+        let nodes = &mut diffs.entry(from.chunk).nodes;
+        if node.harvests_left == node.kind.harvests() {
+            nodes.remove(&from.index);
+        } else {
+            nodes.insert(
+                from.index,
+                NodeDiff {
+                    harvests_left: node.harvests_left,
+                    respawn_in: depleted.map(|depleted| depleted.0.remaining_secs()),
+                },
+            );
+        }
+        diffs.prune(from.chunk);
+    }
+}
@@ -0,0 +1,236 @@
+use bevy::{prelude::*, utils::HashSet};
+
+use super::{
+    chunk::{ChunkCoord, ChunkModified, Terrain},
+    diff::ChunkDiffs,
+    scatter,
+};
+
+/// Offsets smaller than this are treated as no change and dropped from diffs.
+const MIN_OFFSET: f64 = 1e-6;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Reflect)]
+pub enum BrushMode {
+    Raise,
+    Lower,
+    /// Pulls heights towards the height under the brush center.
+    Flatten,
+    /// Pulls heights towards the average of their neighbors.
+    Smooth,
+}
+
+#[derive(Clone, Copy, Debug, Reflect)]
+pub struct Brush {
+    pub mode: BrushMode,
+    /// Radius in world units, the effect fades out towards it.
+    pub radius: f32,
+    /// World units moved at the center for `Raise` and `Lower`, the fraction of the way
+    /// to the target for `Flatten` and `Smooth`.
+    pub strength: f32,
+}
+
+impl Brush {
+    /// How much of the brush applies at `distance` from its center.
+    pub fn falloff(&self, distance: f32) -> f32 {
+        if self.radius <= 0.0 {
+            return 0.0;
+        }
+        let t = (distance / self.radius).clamp(0.0, 1.0);
+        (1.0 - t * t).powi(2)
+    }
+}
+
+/// Applies a brush to the loaded terrain around a world position on the XZ plane.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct TerrainEdit {
+    pub center: Vec2,
+    pub brush: Brush,
+}
+
+impl Terrain {
+    /// Chunks sharing a global cell with the map index of the cell in each of them.
+    ///
+    /// Cells on a chunk border are part of up to four chunks.
+    fn cell_owners(&self, cell: IVec2) -> impl Iterator<Item = (ChunkCoord, usize)> {
+        let size = self.chunk_size as i32;
+        let width = self.chunk_size + 1;
+        let axis = move |value: i32| {
+            let chunk = value.div_euclid(size);
+            let local = value.rem_euclid(size);
+            match local {
+                0 => vec![(chunk, 0), (chunk - 1, size)],
+                _ => vec![(chunk, local)],
+            }
+        };
+
+        axis(cell.y)
+            .into_iter()
+            .flat_map(move |(chunk_y, local_y)| {
+                axis(cell.x).into_iter().map(move |(chunk_x, local_x)| {
+                    (
+                        ChunkCoord(IVec2::new(chunk_x, chunk_y)),
+                        local_x as usize + local_y as usize * width,
+                    )
+                })
+            })
+    }
+
+    /// Normalized height of a global cell, if a chunk containing it is loaded.
+    fn cell_height(&self, cell: IVec2) -> Option<f64> {
+        self.cell_owners(cell).find_map(|(coord, index)| {
+            self.chunks
+                .get(&coord)
+                .map(|chunk| chunk.map.noise_map[index])
+        })
+    }
+
+    /// Sets a global cell's height in every loaded chunk containing it and records it
+    /// in the diffs of all of them, loaded or not. Chunks with the cell in their apron
+    /// are updated and returned as well.
+    fn set_cell_height(
+        &mut self,
+        diffs: &mut ChunkDiffs,
+        cell: IVec2,
+        height: f64,
+    ) -> Vec<ChunkCoord> {
+        let owners: Vec<(ChunkCoord, usize)> = self.cell_owners(cell).collect();
+        let Some(&(coord, index)) = owners
+            .iter()
+            .find(|(coord, _)| self.chunks.contains_key(coord))
+        else {
+            return Vec::new();
+        };
+
+        // Every owner generates the same baseline height for the cell, so they all
+        // store the same offset from it.
+        let current = self.chunks[&coord].map.noise_map[index];
+        let previous_offset = diffs
+            .get(coord)
+            .and_then(|diff| diff.heights.get(&index).copied())
+            .unwrap_or(0.0);
+        let offset = height - (current - previous_offset);
+
+        let mut touched = Vec::new();
+        for (coord, index) in owners {
+            let heights = &mut diffs.entry(coord).heights;
+            if offset.abs() < MIN_OFFSET {
+                heights.remove(&index);
+            } else {
+                heights.insert(index, offset);
+            }
+            diffs.prune(coord);
+
+            if let Some(chunk) = self.chunks.get_mut(&coord) {
+                chunk.map.noise_map[index] = height;
+                touched.push(coord);
+            }
+        }
+
+        // Chunks next to the owners see the cell in their aprons.
+        let size = self.chunk_size as i32;
+        let center = IVec2::new(cell.x.div_euclid(size), cell.y.div_euclid(size));
+        for y in -1..=1 {
+            for x in -1..=1 {
+                let coord = ChunkCoord(center + IVec2::new(x, y));
+                let Some(chunk) = self.chunks.get_mut(&coord) else {
+                    continue;
+                };
+                let local = cell - chunk.map.origin;
+                let inside =
+                    local.cmpge(IVec2::ZERO).all() && local.cmple(IVec2::splat(size)).all();
+                if let (false, Some(index)) = (inside, chunk.map.apron_index(local.x, local.y)) {
+                    chunk.map.apron[index] = height;
+                    touched.push(coord);
+                }
+            }
+        }
+        touched
+    }
+
+    /// Applies a brush and returns the loaded chunks whose heights changed.
+    pub fn edit(&mut self, diffs: &mut ChunkDiffs, edit: &TerrainEdit) -> HashSet<ChunkCoord> {
+        let mut touched = HashSet::new();
+        let Some(chunk) = self.chunk_at(edit.center) else {
+            return touched;
+        };
+        let height_multiplier = chunk.map.height_multiplier() as f64;
+
+        // Global cell `(x, y)` sits at world `(x, -y)`.
+        let center = Vec2::new(edit.center.x, -edit.center.y);
+        let Some(center_height) = self.cell_height(center.round().as_ivec2()) else {
+            return touched;
+        };
+
+        let min = (center - edit.brush.radius).floor().as_ivec2();
+        let max = (center + edit.brush.radius).ceil().as_ivec2();
+
+        // Everything is read before writing so smoothing doesn't see its own results.
+        let mut changes = Vec::new();
+        for y in min.y..=max.y {
+            for x in min.x..=max.x {
+                let cell = IVec2::new(x, y);
+                let weight = edit.brush.falloff(cell.as_vec2().distance(center));
+                if weight <= 0.0 {
+                    continue;
+                }
+                let Some(height) = self.cell_height(cell) else {
+                    continue;
+                };
+
+                let weight = weight as f64;
+                let strength = edit.brush.strength as f64;
+                let new_height = match edit.brush.mode {
+                    BrushMode::Raise => height + strength * weight / height_multiplier,
+                    BrushMode::Lower => height - strength * weight / height_multiplier,
+                    BrushMode::Flatten => {
+                        height + (center_height - height) * (strength * weight).min(1.0)
+                    }
+                    BrushMode::Smooth => {
+                        let neighbors: Vec<f64> = [IVec2::X, IVec2::NEG_X, IVec2::Y, IVec2::NEG_Y]
+                            .into_iter()
+                            .filter_map(|offset| self.cell_height(cell + offset))
+                            .collect();
+                        let average = neighbors.iter().sum::<f64>() / neighbors.len() as f64;
+                        height + (average - height) * (strength * weight).min(1.0)
+                    }
+                };
+                changes.push((cell, new_height.clamp(0.0, 1.0)));
+            }
+        }
+
+        for (cell, height) in changes {
+            touched.extend(self.set_cell_height(diffs, cell, height));
+        }
+        touched
+    }
+}
+
+pub(super) fn apply_terrain_edits(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut images: ResMut<Assets<Image>>,
+    mut edits: EventReader<TerrainEdit>,
+    mut terrain: ResMut<Terrain>,
+    mut diffs: ResMut<ChunkDiffs>,
+    mut modified_events: EventWriter<ChunkModified>,
+) {
+    let mut touched = HashSet::new();
+    for edit in edits.read() {
+        touched.extend(terrain.edit(&mut diffs, edit));
+    }
+
+    for coord in touched {
+        let Some(chunk) = terrain.chunks.get_mut(&coord) else {
+            continue;
+        };
+        scatter::settle(&mut chunk.scatter, &chunk.map);
+        images.insert(&chunk.splat, chunk.map.splat_map());
+
+        let mesh_data = chunk.map.build_mesh();
+        commands.entity(chunk.entity).insert((
+            meshes.add(mesh_data.create_mesh()),
+            mesh_data.create_collider(),
+        ));
+        modified_events.send(ChunkModified(coord));
+    }
+}
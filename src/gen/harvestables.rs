@@ -0,0 +1,121 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use crate::logic::gathering::{Depleted, NodeKind, ResourceNode};
+
+use super::{
+    chunk::ChunkCoord,
+    diff::{ChunkDiff, ScatteredFrom},
+    scatter::{Placement, ScatterKind},
+};
+
+impl ScatterKind {
+    /// Kind of resource node spawned for scattered objects that can be harvested.
+    pub fn node_kind(self) -> Option<NodeKind> {
+        match self {
+            ScatterKind::Tree => Some(NodeKind::Tree),
+            ScatterKind::Bush => Some(NodeKind::Bush),
+            ScatterKind::Rock => Some(NodeKind::Rock),
+            ScatterKind::Grass => None,
+        }
+    }
+}
+
+/// Meshes and materials shared by every spawned node.
+#[derive(Resource)]
+pub struct HarvestableAssets {
+    tree: (Handle<Mesh>, Handle<StandardMaterial>),
+    rock: (Handle<Mesh>, Handle<StandardMaterial>),
+    bush: (Handle<Mesh>, Handle<StandardMaterial>),
+}
+
+impl FromWorld for HarvestableAssets {
+    fn from_world(world: &mut World) -> Self {
+        let mut meshes = world.resource_mut::<Assets<Mesh>>();
+        let tree_mesh = meshes.add(
+            shape::Cylinder {
+                radius: 0.3,
+                height: 3.0,
+                ..Default::default()
+            }
+            .into(),
+        );
+        let rock_mesh = meshes.add(
+            shape::Icosphere {
+                radius: 0.5,
+                subdivisions: 1,
+            }
+            .try_into()
+            .unwrap(),
+        );
+        let bush_mesh = meshes.add(
+            shape::UVSphere {
+                radius: 0.4,
+                ..Default::default()
+            }
+            .into(),
+        );
+
+        let mut materials = world.resource_mut::<Assets<StandardMaterial>>();
+        Self {
+            tree: (tree_mesh, materials.add(Color::rgb(0.4, 0.25, 0.1).into())),
+            rock: (rock_mesh, materials.add(Color::GRAY.into())),
+            bush: (bush_mesh, materials.add(Color::DARK_GREEN.into())),
+        }
+    }
+}
+
+/// Spawns a resource node for every harvestable placement as a child of its chunk,
+/// with whatever was already gathered from it according to the chunk's diff.
+pub(super) fn spawn_nodes(
+    parent: &mut ChildBuilder,
+    chunk: ChunkCoord,
+    placements: &[Placement],
+    diff: Option<&ChunkDiff>,
+    assets: &HarvestableAssets,
+) {
+    for (index, placement) in placements.iter().enumerate() {
+        let Some(kind) = placement.kind.node_kind() else {
+            continue;
+        };
+        let mut node = ResourceNode::new(kind);
+        let node_diff = diff.and_then(|diff| diff.nodes.get(&index));
+        if let Some(node_diff) = node_diff {
+            node.harvests_left = node_diff.harvests_left;
+        }
+
+        let ((mesh, material), collider, offset) = match kind {
+            NodeKind::Tree => (&assets.tree, Collider::cylinder(1.5, 0.3), 1.5),
+            NodeKind::Rock => (&assets.rock, Collider::ball(0.5), 0.2),
+            NodeKind::Bush => (&assets.bush, Collider::ball(0.4), 0.3),
+        };
+
+        let depleted = node.harvests_left == 0;
+        let mut entity = parent.spawn((
+            PbrBundle {
+                mesh: mesh.clone(),
+                material: material.clone(),
+                transform: Transform::from_translation(
+                    placement.position + Vec3::Y * offset * placement.scale,
+                )
+                .with_rotation(Quat::from_rotation_y(placement.rotation))
+                .with_scale(Vec3::splat(placement.scale)),
+                visibility: match depleted {
+                    true => Visibility::Hidden,
+                    false => Visibility::Inherited,
+                },
+                ..Default::default()
+            },
+            RigidBody::Fixed,
+            collider,
+            node,
+            ScatteredFrom { chunk, index },
+        ));
+        if depleted {
+            entity.insert((
+                Depleted::new(kind, node_diff.and_then(|node_diff| node_diff.respawn_in)),
+                ColliderDisabled,
+            ));
+        }
+    }
+}
@@ -1,40 +1,90 @@
 use std::marker::PhantomData;
 
 use bevy::{
-    app::{Plugin, Startup, Update},
-    asset::{Assets, Handle},
+    app::{Plugin, PostUpdate, Update},
     ecs::{
-        component::Component,
         reflect::ReflectResource,
-        system::{Commands, Res, ResMut, Resource},
+        schedule::IntoSystemConfigs,
+        system::{Res, Resource},
     },
-    math::{Vec2, Vec3},
-    pbr::{PbrBundle, StandardMaterial},
+    math::{DVec2, IVec2, Vec2, Vec3},
+    pbr::MaterialPlugin,
     reflect::{std_traits::ReflectDefault, Reflect},
     render::{
         color::Color,
-        mesh::{shape::Plane, Mesh, VertexAttributeValues},
+        mesh::{Indices, Mesh},
         render_resource::PrimitiveTopology,
     },
-    transform::components::Transform,
 };
 use bevy_inspector_egui::{
     inspector_options::ReflectInspectorOptions, quick::ResourceInspectorPlugin, InspectorOptions,
 };
+use bevy_rapier3d::prelude::Collider;
 use noise::{NoiseFn, Perlin};
 use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
 
-use crate::utils::inv_lerp;
+use crate::utils::{inv_lerp, lerp};
 
+use self::{biome::Biome, continent::Continents};
+
+pub mod biome;
+pub mod chunk;
+pub mod continent;
+pub mod diff;
+pub mod edit;
+pub mod harvestables;
 pub mod map;
+pub mod normals;
+pub mod river;
+pub mod roads;
+pub mod scatter;
+pub mod spawn;
+pub mod splat;
+pub mod structures;
+pub mod vegetation;
+pub mod voxel;
 
 pub struct MapPlugin;
 
 impl Plugin for MapPlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
-        app.init_resource::<NoiseMeshHandle>()
-            .add_systems(Startup, spawn_noise)
-            .add_systems(Update, update_noise)
+        app.init_resource::<TerrainTint>()
+            .init_resource::<chunk::Terrain>()
+            .init_resource::<splat::TerrainLayers>()
+            .init_resource::<harvestables::HarvestableAssets>()
+            .init_resource::<river::Rivers>()
+            .init_resource::<river::RiverMaterial>()
+            .init_resource::<structures::Structures>()
+            .init_resource::<roads::Roads>()
+            .init_resource::<structures::StructureAssets>()
+            .add_event::<chunk::ChunkLoaded>()
+            .add_event::<chunk::ChunkModified>()
+            .add_event::<chunk::ChunkUnloaded>()
+            .add_event::<edit::TerrainEdit>()
+            .register_type::<TerrainTint>()
+            .add_plugins(MaterialPlugin::<splat::TerrainMaterial>::default())
+            .init_resource::<diff::ChunkDiffs>()
+            .init_resource::<vegetation::VegetationMaterial>()
+            .init_resource::<vegetation::VegetationSettings>()
+            .register_type::<vegetation::VegetationSettings>()
+            .register_type::<structures::StructurePiece>()
+            .register_type::<structures::PieceShape>()
+            .add_systems(
+                Update,
+                (
+                    chunk::reload_chunks,
+                    chunk::retint_chunks,
+                    chunk::stream_chunks,
+                    edit::apply_terrain_edits,
+                    vegetation::spawn_vegetation,
+                    vegetation::update_vegetation_lod,
+                )
+                    .chain()
+                    .in_set(chunk::ChunkSet),
+            )
+            .add_systems(Update, structures::build_structure_pieces)
+            .add_systems(PostUpdate, diff::record_node_diffs)
             .init_resource::<NoiseConfig>()
             .register_type::<NoiseConfig>()
             .register_type::<TerrainType>()
@@ -42,79 +92,19 @@ impl Plugin for MapPlugin {
     }
 }
 
-#[derive(Resource, Default)]
-struct NoiseMeshHandle(Handle<Mesh>);
-
-#[derive(Component)]
-struct NoiseMarker;
-
-/*
-    TODO: rewrite this code
-*/
-
-fn spawn_noise(
-    mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
-    mut noise_mesh_handle: ResMut<NoiseMeshHandle>,
-    config: Res<NoiseConfig>,
-) {
-    let subdivisions = config.subdivisions;
-    let noise = Noise::from(config).generate_map();
-
-    let mut plane = Mesh::new(PrimitiveTopology::TriangleList).with_inserted_attribute(
-        Mesh::ATTRIBUTE_POSITION,
-        {
-            let mut vertices = vec![Vec3::ZERO; noise.width() * noise.height()];
-            for y in 0..noise.height() {
-                for x in 0..noise.width() {
-                    todo!()
-                }
-            }
-            vertices
-        },
-    );
-    colorize(&mut plane, &noise);
-    let handle = meshes.add(plane);
-    noise_mesh_handle.0 = handle.clone();
-    commands
-        .spawn(PbrBundle {
-            mesh: handle,
-            material: materials.add(Color::rgb(1., 1., 1.).into()),
-            transform: Transform::from_xyz(0.0, 5.0, 0.0),
-            ..Default::default()
-        })
-        .insert(NoiseMarker);
-}
-
-fn update_noise(
-    noise_mesh_handle: ResMut<NoiseMeshHandle>,
-    mut meshes: ResMut<Assets<Mesh>>,
-    config: Res<NoiseConfig>,
-) {
-    let noise = Noise::from(config).generate_map();
-
-    if let Some(mesh) = meshes.get_mut(noise_mesh_handle.0.clone()) {
-        colorize(mesh, &noise)
-    }
-}
-
-fn colorize(plane: &mut Mesh, noise: &Noise<Generated>) {
-    if let Some(VertexAttributeValues::Float32x3(_positions)) =
-        plane.attribute(Mesh::ATTRIBUTE_POSITION)
-    {
-        let colors = noise.colorize_map();
-        plane.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
-    }
-}
-
-#[derive(Reflect, Resource, InspectorOptions)]
+#[derive(Reflect, Resource, InspectorOptions, Clone, Serialize, Deserialize)]
 #[reflect(Resource, InspectorOptions)]
+#[serde(default)]
 pub struct NoiseConfig {
-    width: usize,
-    height: usize,
+    /// Number of cells along each side of a chunk.
+    #[inspector(min = 1)]
+    chunk_size: usize,
+    /// How many chunks are kept loaded in each direction around the camera.
+    view_distance: u32,
     #[inspector(min = 0.0)]
     scale: f64,
+    #[inspector(min = 0.0)]
+    height_multiplier: f32,
     seed: u32,
     subdivisions: u32,
     octaves: usize,
@@ -122,6 +112,15 @@ pub struct NoiseConfig {
     persistance: f64,
     lacunarity: f64,
     offset: Vec2,
+    /// Rough size of a continental plate in cells.
+    #[inspector(min = 32.0)]
+    plate_size: f64,
+    /// Normalized height continents rise and oceans sink by, `0` turns them off.
+    #[inspector(min = 0.0, max = 1.0)]
+    continent_strength: f64,
+    /// Normalized height above which terrain is covered in snow.
+    #[inspector(min = 0.0, max = 1.0)]
+    snow_line: f64,
     draw_mode: DrawMode,
     regions: Vec<TerrainType>,
 }
@@ -129,20 +128,24 @@ pub struct NoiseConfig {
 impl Default for NoiseConfig {
     fn default() -> Self {
         Self {
-            width: 100,
-            height: 100,
+            chunk_size: 64,
+            view_distance: 3,
             scale: 22.0,
+            height_multiplier: 10.0,
             seed: Default::default(),
             subdivisions: 98,
             octaves: 4,
             persistance: 0.5,
             lacunarity: 2.0,
             offset: Default::default(),
+            plate_size: 1536.0,
+            continent_strength: 0.3,
+            snow_line: 0.9,
             regions: vec![
                 TerrainType {
                     height: 0.4,
                     color: Color::BLUE,
-                    name: "Water".to_string(),
+                    name: WATER_REGION.to_string(),
                 },
                 TerrainType {
                     height: 1.0,
@@ -155,30 +158,108 @@ impl Default for NoiseConfig {
     }
 }
 
-enum Generated {}
-enum Undefined {}
+/// Name of the region treated as the sea.
+pub const WATER_REGION: &str = "Water";
+
+const SNOW_COLOR: Color = Color::rgb(0.95, 0.95, 1.0);
+const AUTUMN_COLOR: Color = Color::rgb(0.8, 0.45, 0.1);
+const ROAD_COLOR: Color = Color::rgb(0.5, 0.4, 0.28);
+
+/// Seasonal adjustments applied on top of the region colors.
+#[derive(Reflect, Resource, Default, Clone, Copy, PartialEq)]
+#[reflect(Resource)]
+pub struct TerrainTint {
+    /// How far the snow line is lowered, in normalized height.
+    pub snow_line_offset: f64,
+    /// How much land regions shift towards autumn colors, in `0.0..=1.0`.
+    pub autumn: f32,
+}
+
+impl NoiseConfig {
+    /// World height of the "Water" region's upper bound, if the config has one.
+    pub fn sea_level(&self) -> Option<f32> {
+        self.normalized_sea_level()
+            .map(|height| height as f32 * self.height_multiplier)
+    }
+
+    /// Upper bound of the "Water" region in normalized noise height.
+    pub fn normalized_sea_level(&self) -> Option<f64> {
+        self.regions
+            .iter()
+            .find(|region| region.name == WATER_REGION)
+            .map(|region| region.height)
+    }
+
+    pub fn chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+
+    /// Highest world height the terrain can reach.
+    pub fn height_multiplier(&self) -> f32 {
+        self.height_multiplier
+    }
+
+    pub fn view_distance(&self) -> u32 {
+        self.view_distance
+    }
+
+    /// Width of the square of chunks kept loaded around the camera, in world units.
+    pub fn view_extent(&self) -> f32 {
+        ((self.view_distance * 2 + 1) as usize * self.chunk_size) as f32
+    }
+}
+
+/// Perlin noise rarely gets near its theoretical bounds, so summed octaves are stretched
+/// by this much before being clamped to `0.0..=1.0`.
+const HEIGHT_SPREAD: f64 = 1.5;
+
+pub enum Generated {}
+pub enum Undefined {}
 
 #[derive(typed_builder::TypedBuilder)]
 pub struct Noise<Map> {
     #[builder(default = noise::Perlin::new(seed.0))]
     perlin: Perlin,
+    #[builder(default = noise::Perlin::new(seed.0.wrapping_add(1)))]
+    moisture_perlin: Perlin,
     seed: u32,
+    /// Global cell of the map's first sample, so separately generated maps line up.
+    #[builder(default)]
+    origin: IVec2,
+    /// Global cells between neighboring samples, above `1` for coarse overview maps.
+    #[builder(default = 1)]
+    step: usize,
     width: usize,
     height: usize,
     scale: f64,
+    height_multiplier: f32,
     octaves: usize,
     persistance: f64,
     lacunarity: f64,
     offset: Vec2,
+    continents: Continents,
+    snow_line: f64,
+    #[builder(default)]
+    tint: TerrainTint,
     regions: Vec<TerrainType>,
     draw_mode: DrawMode,
     #[builder(default = vec![])]
     noise_map: Vec<f64>,
+    /// Extra moisture from nearby rivers, empty until rivers are carved into the map.
+    #[builder(default = vec![])]
+    river_moisture: Vec<f32>,
+    /// How much of each cell is covered by road, empty until roads are paved.
+    #[builder(default = vec![])]
+    road_mask: Vec<f32>,
+    /// Heights of the map with one more cell on every side, so normals on its border can
+    /// look past it. Only the outer ring is read, empty if the map has none.
+    #[builder(default = vec![])]
+    apron: Vec<f64>,
     #[builder(default = std::marker::PhantomData)]
     _marker: PhantomData<Map>,
 }
 
-#[derive(Reflect, InspectorOptions, Default, Clone)]
+#[derive(Reflect, InspectorOptions, Default, Clone, Serialize, Deserialize)]
 #[reflect(InspectorOptions, Default)]
 pub struct TerrainType {
     pub height: f64,
@@ -186,48 +267,53 @@ pub struct TerrainType {
     pub name: String,
 }
 
-#[derive(Reflect, InspectorOptions, Default, Clone)]
+#[derive(Reflect, InspectorOptions, Default, Clone, Serialize, Deserialize)]
 #[reflect(InspectorOptions, Default)]
 pub enum DrawMode {
     NoiseMap,
     #[default]
     ColorMap,
     Mesh,
+    /// Surface nets over a density field with caves below the heightmap surface.
+    Voxel,
 }
 
 // region: From impls
 
-impl From<NoiseConfig> for Noise<Undefined> {
-    fn from(value: NoiseConfig) -> Self {
+impl From<&NoiseConfig> for Noise<Undefined> {
+    fn from(value: &NoiseConfig) -> Self {
+        // Neighboring chunks share their border row so there are no gaps between them.
         Noise::builder()
-            .width(value.width)
-            .height(value.height)
+            .width(value.chunk_size + 1)
+            .height(value.chunk_size + 1)
             .draw_mode(value.draw_mode.clone())
             .lacunarity(value.lacunarity)
             .octaves(value.octaves)
             .offset(value.offset)
+            .continents(Continents::new(
+                value.seed,
+                value.plate_size,
+                value.continent_strength,
+            ))
+            .snow_line(value.snow_line)
             .regions(value.regions.clone())
             .persistance(value.persistance)
             .scale(value.scale)
+            .height_multiplier(value.height_multiplier)
             .seed(value.seed)
             .build()
     }
 }
 
+impl From<NoiseConfig> for Noise<Undefined> {
+    fn from(value: NoiseConfig) -> Self {
+        Noise::from(&value)
+    }
+}
+
 impl From<Res<'_, NoiseConfig>> for Noise<Undefined> {
     fn from(value: Res<'_, NoiseConfig>) -> Self {
-        Noise::builder()
-            .width(value.width)
-            .height(value.height)
-            .draw_mode(value.draw_mode.clone())
-            .lacunarity(value.lacunarity)
-            .octaves(value.octaves)
-            .offset(value.offset)
-            .regions(value.regions.clone())
-            .persistance(value.persistance)
-            .scale(value.scale)
-            .seed(value.seed)
-            .build()
+        Noise::from(&*value)
     }
 }
 
@@ -241,9 +327,39 @@ impl<Map> Noise<Map> {
     pub fn width(&self) -> usize {
         self.width
     }
+
+    pub fn seed(&self) -> u32 {
+        self.seed
+    }
+
+    pub fn height_multiplier(&self) -> f32 {
+        self.height_multiplier
+    }
 }
 
 impl Noise<Undefined> {
+    pub fn with_tint(mut self, tint: TerrainTint) -> Self {
+        self.tint = tint;
+        self
+    }
+
+    pub fn with_origin(mut self, origin: IVec2) -> Self {
+        self.origin = origin;
+        self
+    }
+
+    /// Samples every `step`th global cell, covering `step` times the area.
+    pub fn with_step(mut self, step: usize) -> Self {
+        self.step = step.max(1);
+        self
+    }
+
+    pub fn with_size(mut self, width: usize, height: usize) -> Self {
+        self.width = width;
+        self.height = height;
+        self
+    }
+
     pub fn generate_map(self) -> Noise<Generated> {
         let mut noise_map = vec![0.0; self.width * self.height];
 
@@ -256,61 +372,73 @@ impl Noise<Undefined> {
             *i = Vec2::new(offset_x, offset_y);
         }
 
-        let mut max_noise_height = f64::MIN;
-        let mut min_noise_height = f64::MAX;
+        // Every chunk has to agree on the height of a cell, so heights are normalized
+        // against the largest possible sum instead of the min and max of this map.
+        let mut max_possible_height = 0.0;
+        let mut amplitude = 1.0;
+        for _ in 0..self.octaves {
+            max_possible_height += amplitude;
+            amplitude *= self.persistance;
+        }
 
-        let half_width = (self.width / 2) as f64;
-        let half_height = (self.height / 2) as f64;
+        let continents = self
+            .continents
+            .layer(self.origin, self.step, self.width, self.height);
 
         for y in 0..self.height {
             for x in 0..self.width {
+                let global_x = (self.origin.x as f64) + (x * self.step) as f64;
+                let global_y = (self.origin.y as f64) + (y * self.step) as f64;
+
                 let mut amplitude = 1.0;
                 let mut frequency = 1.0;
                 let mut noise_height = 0.0;
 
                 for i in &octave_offsets {
-                    let sample_x = (x as f64 - half_width) / self.scale * frequency + (i.x as f64);
-                    let sample_y = (y as f64 - half_height) / self.scale * frequency + (i.y as f64);
+                    let sample_x = global_x / self.scale * frequency + (i.x as f64);
+                    let sample_y = global_y / self.scale * frequency + (i.y as f64);
 
-                    let perlin_value = self.perlin.get([sample_x, sample_y]) * 2.0 - 1.0;
+                    let perlin_value = self.perlin.get([sample_x, sample_y]);
                     noise_height += perlin_value * amplitude;
 
                     amplitude *= self.persistance;
                     frequency *= self.lacunarity;
                 }
 
-                if noise_height > max_noise_height {
-                    max_noise_height = noise_height;
-                } else if noise_height < min_noise_height {
-                    min_noise_height = noise_height;
-                }
-                noise_map[x + y * self.width] = noise_height;
-            }
-        }
-
-        for y in 0..self.height {
-            for x in 0..self.width {
-                noise_map[x + y * self.width] = inv_lerp(
-                    min_noise_height,
-                    max_noise_height,
-                    noise_map[x + y * self.width],
+                let local = inv_lerp(
+                    -max_possible_height,
+                    max_possible_height,
+                    noise_height * HEIGHT_SPREAD,
                 )
+                .clamp(0.0, 1.0);
+                noise_map[x + y * self.width] =
+                    continents.apply(DVec2::new(global_x, global_y), local);
             }
         }
 
         Noise {
             noise_map,
             perlin: self.perlin,
+            moisture_perlin: self.moisture_perlin,
             seed: self.seed,
+            origin: self.origin,
+            step: self.step,
             width: self.width,
-            height: self.width,
+            height: self.height,
             scale: self.scale,
+            height_multiplier: self.height_multiplier,
             octaves: self.octaves,
             persistance: self.persistance,
             lacunarity: self.lacunarity,
             offset: self.offset,
+            continents: self.continents,
+            snow_line: self.snow_line,
+            tint: self.tint,
             regions: self.regions,
             draw_mode: self.draw_mode,
+            river_moisture: Vec::new(),
+            road_mask: Vec::new(),
+            apron: Vec::new(),
             _marker: PhantomData,
         }
     }
@@ -331,14 +459,19 @@ impl Noise<Generated> {
                 }
                 colors
             }
-            DrawMode::ColorMap | DrawMode::Mesh => {
+            DrawMode::ColorMap | DrawMode::Mesh | DrawMode::Voxel => {
                 let mut colors = vec![[0.0, 0.0, 0.0, 0.0]; self.width * self.height];
                 for y in 0..self.height {
                     for x in 0..self.width {
                         let current_height = self.noise_map[x + y * self.width];
                         for region in self.regions.iter() {
                             if current_height <= region.height {
-                                colors[x + y * self.width] = region.color.into();
+                                let color = crate::utils::color_lerp(
+                                    self.tinted_color(region, current_height),
+                                    ROAD_COLOR,
+                                    self.road_at(x, y),
+                                );
+                                colors[x + y * self.width] = color.into();
                                 break;
                             }
                         }
@@ -349,20 +482,141 @@ impl Noise<Generated> {
         }
     }
 
-    pub fn generate_mesh(&self) -> MeshData {
-        let top_left_x = (self.width - 1) as f32 / -2.0;
-        let top_left_z = (self.height - 1) as f32 / 2.0;
+    pub fn height_at(&self, x: usize, y: usize) -> f64 {
+        self.noise_map[x + y * self.width]
+    }
+
+    /// How much of a map cell is covered by road, in `0.0..=1.0`.
+    pub fn road_at(&self, x: usize, y: usize) -> f32 {
+        self.road_mask
+            .get(x + y * self.width)
+            .copied()
+            .unwrap_or(0.0)
+    }
+
+    /// Map cell closest to a world position on the XZ plane.
+    pub fn world_to_map(&self, position: Vec2) -> Option<(usize, usize)> {
+        let x = (position.x - self.origin.x as f32).round();
+        let y = (-position.y - self.origin.y as f32).round();
+
+        let inside = x >= 0.0 && y >= 0.0 && x < self.width as f32 && y < self.height as f32;
+        inside.then_some((x as usize, y as usize))
+    }
 
+    /// World position of the terrain surface at a map cell.
+    pub fn map_to_world(&self, x: usize, y: usize) -> Vec3 {
+        self.map_to_local(x, y) + self.world_origin()
+    }
+
+    /// Position of a map cell relative to [`Self::world_origin`].
+    pub fn map_to_local(&self, x: usize, y: usize) -> Vec3 {
+        Vec3::new(
+            x as f32,
+            self.height_at(x, y) as f32 * self.height_multiplier,
+            -(y as f32),
+        )
+    }
+
+    /// World position of the map's first cell at zero height, where its mesh is placed.
+    pub fn world_origin(&self) -> Vec3 {
+        Vec3::new(self.origin.x as f32, 0.0, -self.origin.y as f32)
+    }
+
+    /// Normalized height between cells, interpolated from the four surrounding ones.
+    pub fn sample_height(&self, x: f32, y: f32) -> f64 {
+        let x = x.clamp(0.0, (self.width - 1) as f32);
+        let y = y.clamp(0.0, (self.height - 1) as f32);
+        let (x0, y0) = (x.floor() as usize, y.floor() as usize);
+        let (x1, y1) = ((x0 + 1).min(self.width - 1), (y0 + 1).min(self.height - 1));
+        let (tx, ty) = ((x - x0 as f32) as f64, (y - y0 as f32) as f64);
+
+        let top = lerp(self.height_at(x0, y0), self.height_at(x1, y0), tx);
+        let bottom = lerp(self.height_at(x0, y1), self.height_at(x1, y1), tx);
+        lerp(top, bottom, ty)
+    }
+
+    /// Largest height difference to the neighboring cells, in world units.
+    pub fn slope_at(&self, x: usize, y: usize) -> f32 {
+        let height = self.height_at(x, y);
+        let neighbors = [
+            (x.wrapping_sub(1), y),
+            (x + 1, y),
+            (x, y.wrapping_sub(1)),
+            (x, y + 1),
+        ];
+        neighbors
+            .into_iter()
+            .filter(|&(nx, ny)| nx < self.width && ny < self.height)
+            .map(|(nx, ny)| (self.height_at(nx, ny) - height).abs() as f32)
+            .fold(0.0, f32::max)
+            * self.height_multiplier
+    }
+
+    /// World height of the terrain at a world position on the XZ plane.
+    pub fn height_at_world(&self, position: Vec2) -> Option<f32> {
+        self.world_to_map(position)
+            .map(|(x, y)| self.height_at(x, y) as f32 * self.height_multiplier)
+    }
+
+    /// Upper bound of the "Water" region, `0.0` if there is none.
+    pub fn sea_level(&self) -> f64 {
+        self.regions
+            .iter()
+            .find(|region| region.name == WATER_REGION)
+            .map_or(0.0, |region| region.height)
+    }
+
+    /// Low frequency moisture in `0.0..=1.0` at a map position, wetter next to rivers.
+    pub fn moisture_at(&self, x: usize, y: usize) -> f32 {
+        let global_x = (self.origin.x as f64) + (x * self.step) as f64;
+        let global_y = (self.origin.y as f64) + (y * self.step) as f64;
+        let sample_x = (global_x + self.offset.x as f64) / (self.scale * 4.0);
+        let sample_y = (global_y + self.offset.y as f64) / (self.scale * 4.0);
+        let moisture = self.moisture_perlin.get([sample_x, sample_y]) * 0.5 + 0.5;
+        let river = self
+            .river_moisture
+            .get(x + y * self.width)
+            .copied()
+            .unwrap_or(0.0);
+        (moisture as f32 + river).clamp(0.0, 1.0)
+    }
+
+    pub fn biome_at(&self, x: usize, y: usize, sea_level_temperature: f32) -> Biome {
+        let height = self.height_at(x, y);
+        let sea_level = self.sea_level();
+        let temperature = biome::temperature_at(sea_level_temperature, height, sea_level);
+        Biome::classify(height, sea_level, temperature, self.moisture_at(x, y))
+    }
+
+    fn tinted_color(&self, region: &TerrainType, height: f64) -> Color {
+        if region.name == WATER_REGION {
+            return region.color;
+        }
+        if height >= self.snow_line - self.tint.snow_line_offset {
+            return SNOW_COLOR;
+        }
+        crate::utils::color_lerp(region.color, AUTUMN_COLOR, self.tint.autumn)
+    }
+
+    /// Builds the mesh for the map's draw mode relative to [`Self::world_origin`].
+    pub fn build_mesh(&self) -> MeshData {
+        match self.draw_mode {
+            DrawMode::Voxel => self.generate_voxel_mesh(),
+            _ => self.generate_mesh(),
+        }
+    }
+
+    /// Builds the heightmap mesh relative to [`Self::world_origin`].
+    pub fn generate_mesh(&self) -> MeshData {
         let mut mesh_data = MeshData::new(self.width, self.height);
+        mesh_data.colors = self.colorize_map();
         let mut vertex_index: usize = 0;
 
         for y in 0..self.height {
             for x in 0..self.width {
-                mesh_data.vertices[vertex_index] = Vec3::new(
-                    top_left_x + x as f32,
-                    self.noise_map[x + y * self.width] as f32,
-                    top_left_z - y as f32,
-                );
+                mesh_data.vertices[vertex_index] = self.map_to_local(x, y);
+                mesh_data.normals[vertex_index] = self.normal_at(x, y);
+                mesh_data.tangents[vertex_index] = self.tangent_at(x, y);
                 mesh_data.uvs[vertex_index] =
                     Vec2::new(x as f32 / self.width as f32, y as f32 / self.height as f32);
 
@@ -390,6 +644,10 @@ pub struct MeshData {
     pub vertices: Vec<Vec3>,
     pub triangles: Vec<usize>,
     pub uvs: Vec<Vec2>,
+    pub colors: Vec<[f32; 4]>,
+    pub normals: Vec<Vec3>,
+    /// Per vertex tangents with handedness in `w`, left empty if there are none.
+    pub tangents: Vec<[f32; 4]>,
     triangle_index: usize,
 }
 
@@ -399,6 +657,9 @@ impl MeshData {
             vertices: vec![Vec3::ZERO; mesh_width * mesh_height],
             triangles: vec![0; (mesh_width - 1) * (mesh_height - 1) * 6],
             uvs: vec![Vec2::ZERO; mesh_width * mesh_height],
+            colors: vec![[1.0; 4]; mesh_width * mesh_height],
+            normals: vec![Vec3::Y; mesh_width * mesh_height],
+            tangents: vec![[1.0, 0.0, 0.0, 1.0]; mesh_width * mesh_height],
             triangle_index: 0,
         }
     }
@@ -411,10 +672,25 @@ impl MeshData {
     }
 
     pub fn create_mesh(&self) -> Mesh {
+        let indices = self.triangles.iter().map(|&i| i as u32).collect();
         let mut mesh = Mesh::new(PrimitiveTopology::TriangleList)
             .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, self.vertices.clone())
-            .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, self.uvs.clone());
-        mesh.compute_flat_normals();
+            .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, self.normals.clone())
+            .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, self.uvs.clone())
+            .with_inserted_attribute(Mesh::ATTRIBUTE_COLOR, self.colors.clone());
+        if !self.tangents.is_empty() {
+            mesh.insert_attribute(Mesh::ATTRIBUTE_TANGENT, self.tangents.clone());
+        }
+        mesh.set_indices(Some(Indices::U32(indices)));
         mesh
     }
+
+    pub fn create_collider(&self) -> Collider {
+        let indices = self
+            .triangles
+            .chunks_exact(3)
+            .map(|triangle| [triangle[0] as u32, triangle[1] as u32, triangle[2] as u32])
+            .collect();
+        Collider::trimesh(self.vertices.clone(), indices)
+    }
 }
@@ -0,0 +1,94 @@
+//! Smooth normals and tangents for heightmap meshes.
+//!
+//! Normals come from central differences of the heights, which on a chunk's border need
+//! the cells just past it. Chunks are generated one cell larger on every side and that
+//! outer ring is kept as the map's apron, so both chunks sharing a border see the same
+//! heights around it and light it the same way.
+
+use bevy::math::Vec3;
+
+use super::{Generated, Noise};
+
+fn crop<T: Copy>(values: &[T], outer_width: usize, width: usize, height: usize) -> Vec<T> {
+    (1..=height)
+        .flat_map(|y| {
+            values[y * outer_width + 1..y * outer_width + 1 + width]
+                .iter()
+                .copied()
+        })
+        .collect()
+}
+
+impl Noise<Generated> {
+    /// Splits a map generated one cell larger on every side into the inner map, keeping
+    /// the outer ring as its apron.
+    pub fn split_apron(mut self) -> Self {
+        let outer_width = self.width;
+        let (width, height) = (self.width - 2, self.height - 2);
+
+        self.apron = self.noise_map.clone();
+        self.noise_map = crop(&self.noise_map, outer_width, width, height);
+        if !self.river_moisture.is_empty() {
+            self.river_moisture = crop(&self.river_moisture, outer_width, width, height);
+        }
+        if !self.road_mask.is_empty() {
+            self.road_mask = crop(&self.road_mask, outer_width, width, height);
+        }
+        self.origin += 1;
+        self.width = width;
+        self.height = height;
+        self
+    }
+
+    /// Index into the apron of a map position up to one cell outside the map.
+    pub(super) fn apron_index(&self, x: i32, y: i32) -> Option<usize> {
+        let (outer_width, outer_height) = (self.width as i32 + 2, self.height as i32 + 2);
+        let (x, y) = (x + 1, y + 1);
+        let inside = x >= 0 && y >= 0 && x < outer_width && y < outer_height;
+        (inside && !self.apron.is_empty()).then_some((x + y * outer_width) as usize)
+    }
+
+    /// Normalized height at a map position up to one cell outside the map. Without an
+    /// apron the nearest cell on the border stands in.
+    pub fn height_around(&self, x: i32, y: i32) -> f64 {
+        let inside = x >= 0 && y >= 0 && x < self.width as i32 && y < self.height as i32;
+        if inside {
+            return self.height_at(x as usize, y as usize);
+        }
+        match self.apron_index(x, y) {
+            Some(index) => self.apron[index],
+            None => self.height_at(
+                x.clamp(0, self.width as i32 - 1) as usize,
+                y.clamp(0, self.height as i32 - 1) as usize,
+            ),
+        }
+    }
+
+    /// Change in world height per cell along the map's `x` and `y`.
+    fn gradient(&self, x: usize, y: usize) -> (f32, f32) {
+        let (x, y) = (x as i32, y as i32);
+        let dx = self.height_around(x + 1, y) - self.height_around(x - 1, y);
+        let dy = self.height_around(x, y + 1) - self.height_around(x, y - 1);
+        (
+            dx as f32 * self.height_multiplier / 2.0,
+            dy as f32 * self.height_multiplier / 2.0,
+        )
+    }
+
+    /// Surface normal at a map position, relative to the chunk.
+    pub fn normal_at(&self, x: usize, y: usize) -> Vec3 {
+        // Map `y` grows towards world -Z, so its slope flips sign along Z.
+        let (dx, dy) = self.gradient(x, y);
+        Vec3::new(-dx, 1.0, dy).normalize()
+    }
+
+    /// Tangent along the direction the mesh's `u` coordinate grows in, world +X.
+    pub fn tangent_at(&self, x: usize, y: usize) -> [f32; 4] {
+        let normal = self.normal_at(x, y);
+        let (dx, _) = self.gradient(x, y);
+        let along = Vec3::new(1.0, dx, 0.0);
+        let tangent = (along - normal * along.dot(normal)).normalize();
+        // `v` grows with map `y`, towards world -Z, which is `normal × tangent`.
+        [tangent.x, tangent.y, tangent.z, 1.0]
+    }
+}
@@ -0,0 +1,441 @@
+//! Rivers traced over a coarse overview of the terrain.
+//!
+//! The world is split into square regions. Each region samples the terrain every
+//! [`RIVER_STEP`] cells with a margin around it, runs a D8 flow pass over that, and traces
+//! rivers from its own wet highlands downhill until they reach the sea, end in a pit or
+//! leave the margin. Chunks then carve the rivers of their region and its neighbors into
+//! their maps.
+//!
+//! Regions are traced independently, so where a river flows on into a neighboring region
+//! it can meet a river traced there and share its course with it to the mouth.
+
+use bevy::{
+    prelude::*,
+    render::{mesh::Indices, render_resource::PrimitiveTopology},
+    utils::HashMap,
+};
+
+use super::{chunk::ChunkCoord, Generated, Noise, NoiseConfig};
+
+/// Global cells between two samples of the overview.
+const RIVER_STEP: usize = 4;
+/// Overview samples along each side of a region.
+const REGION_SAMPLES: i32 = 64;
+/// Overview samples traced past each side of a region.
+const REGION_MARGIN: i32 = 32;
+/// Each block of this many samples squared gets at most one source, at its highest point.
+const SOURCE_BLOCK: i32 = 8;
+/// Normalized height above sea level a source has to be at.
+const MIN_SOURCE_HEIGHT: f64 = 0.2;
+const MIN_SOURCE_MOISTURE: f32 = 0.55;
+/// Shortest river kept, in overview samples.
+const MIN_RIVER_LENGTH: usize = 6;
+/// Catmull-Rom points added between two traced samples.
+const SPLINE_SUBDIVISIONS: usize = 4;
+
+/// How far the water surface sits below the terrain it was traced over.
+const WATER_DROP: f32 = 0.3;
+/// Depth of the channel at its center, below the water surface.
+const RIVER_DEPTH: f32 = 1.2;
+/// Width of the bank sloping up from the water's edge on each side.
+pub(super) const BANK_WIDTH: f32 = 3.0;
+/// How far from the water's edge rivers still add moisture.
+const MOISTURE_REACH: f32 = 12.0;
+const MOISTURE_BONUS: f32 = 0.35;
+
+const RIVER_COLOR: Color = Color::rgba(0.15, 0.35, 0.6, 0.75);
+
+#[derive(Clone, Copy, Debug)]
+pub struct RiverPoint {
+    /// World position on the XZ plane.
+    pub position: Vec2,
+    /// World height of the water surface.
+    pub height: f32,
+    pub width: f32,
+}
+
+/// A river from its source to its mouth, as a densely sampled spline.
+#[derive(Clone, Debug)]
+pub struct River {
+    pub points: Vec<RiverPoint>,
+    min: Vec2,
+    max: Vec2,
+}
+
+impl River {
+    fn new(points: Vec<RiverPoint>) -> Self {
+        let reach = points
+            .iter()
+            .map(|point| point.width / 2.0 + BANK_WIDTH + MOISTURE_REACH)
+            .fold(0.0, f32::max);
+        let min = points.iter().fold(Vec2::MAX, |min, p| min.min(p.position)) - reach;
+        let max = points.iter().fold(Vec2::MIN, |max, p| max.max(p.position)) + reach;
+        Self { points, min, max }
+    }
+
+    /// Distance from a world position on the XZ plane to the water's edge, negative in
+    /// the water.
+    pub fn distance(&self, position: Vec2) -> f32 {
+        self.points
+            .windows(2)
+            .map(|segment| {
+                let (a, b) = (segment[0], segment[1]);
+                let (distance, t) = segment_distance(position, a.position, b.position);
+                distance - (a.width + (b.width - a.width) * t) / 2.0
+            })
+            .fold(f32::MAX, f32::min)
+    }
+
+    /// Whether anything of the river, including its banks, reaches into the rectangle.
+    pub fn overlaps(&self, min: Vec2, max: Vec2) -> bool {
+        self.min.cmple(max).all() && self.max.cmpge(min).all()
+    }
+}
+
+/// Rivers of every region generated so far.
+#[derive(Resource, Default)]
+pub struct Rivers {
+    regions: HashMap<IVec2, Vec<River>>,
+}
+
+impl Rivers {
+    pub fn clear(&mut self) {
+        self.regions.clear();
+    }
+
+    /// Rivers that could reach into a chunk, tracing the regions around it if needed.
+    pub fn near_chunk(&mut self, config: &NoiseConfig, coord: ChunkCoord) -> Vec<&River> {
+        let origin = coord.origin(config.chunk_size());
+        let size = config.chunk_size() as i32;
+        self.near_cells(config, origin, origin + IVec2::splat(size))
+    }
+
+    /// Rivers that could reach into the global cells from `first` to `last`, tracing
+    /// the regions around them if needed.
+    pub fn near_cells(&mut self, config: &NoiseConfig, first: IVec2, last: IVec2) -> Vec<&River> {
+        let region_cells = REGION_SAMPLES * RIVER_STEP as i32;
+        let region_of = |cell: IVec2| {
+            IVec2::new(
+                cell.x.div_euclid(region_cells),
+                cell.y.div_euclid(region_cells),
+            )
+        };
+        // Rivers are traced up to the margin past their region, so one more region
+        // around the cells is enough.
+        let (min_region, max_region) = (region_of(first) - 1, region_of(last) + 1);
+
+        let regions: Vec<IVec2> = (min_region.y..=max_region.y)
+            .flat_map(|y| (min_region.x..=max_region.x).map(move |x| IVec2::new(x, y)))
+            .collect();
+        for &region in &regions {
+            self.regions
+                .entry(region)
+                .or_insert_with(|| trace_region(config, region));
+        }
+
+        let min = Vec2::new(first.x as f32, -last.y as f32);
+        let max = Vec2::new(last.x as f32, -first.y as f32);
+        regions
+            .iter()
+            .flat_map(|region| &self.regions[region])
+            .filter(|river| river.overlaps(min, max))
+            .collect()
+    }
+}
+
+/// Runs flow direction and accumulation over a region and traces its rivers.
+fn trace_region(config: &NoiseConfig, region: IVec2) -> Vec<River> {
+    let samples = (REGION_SAMPLES + REGION_MARGIN * 2) as usize;
+    let first = region * REGION_SAMPLES - REGION_MARGIN;
+    let overview = Noise::from(config)
+        .with_origin(first * RIVER_STEP as i32)
+        .with_step(RIVER_STEP)
+        .with_size(samples, samples)
+        .generate_map();
+    let sea_level = overview.sea_level();
+    let index = |x: i32, y: i32| x as usize + y as usize * samples;
+    let inside = |x: i32, y: i32| x >= 0 && y >= 0 && x < samples as i32 && y < samples as i32;
+
+    // D8: every sample drains into its steepest lower neighbor, if it has one.
+    let mut flow = vec![None; samples * samples];
+    for y in 0..samples as i32 {
+        for x in 0..samples as i32 {
+            let height = overview.height_at(x as usize, y as usize);
+            let mut steepest = 0.0;
+            for (dx, dy) in NEIGHBORS {
+                let (nx, ny) = (x + dx, y + dy);
+                if !inside(nx, ny) {
+                    continue;
+                }
+                let distance = ((dx * dx + dy * dy) as f64).sqrt();
+                let drop = (height - overview.height_at(nx as usize, ny as usize)) / distance;
+                if drop > steepest {
+                    steepest = drop;
+                    flow[index(x, y)] = Some((nx, ny));
+                }
+            }
+        }
+    }
+
+    // Accumulation: each sample passes everything that drained into it downhill.
+    let mut order: Vec<usize> = (0..samples * samples).collect();
+    order.sort_by(|&a, &b| overview.noise_map[b].total_cmp(&overview.noise_map[a]));
+    let mut accumulation = vec![1.0_f32; samples * samples];
+    for &i in &order {
+        if let Some((nx, ny)) = flow[i] {
+            accumulation[index(nx, ny)] += accumulation[i];
+        }
+    }
+
+    // Sources are the highest wet sample of each block in the region itself. Blocks are
+    // aligned to the global grid so neighboring regions never pick the same source.
+    let mut sources = Vec::new();
+    for block_y in (REGION_MARGIN..REGION_MARGIN + REGION_SAMPLES).step_by(SOURCE_BLOCK as usize) {
+        for block_x in
+            (REGION_MARGIN..REGION_MARGIN + REGION_SAMPLES).step_by(SOURCE_BLOCK as usize)
+        {
+            let highest = (block_y..block_y + SOURCE_BLOCK)
+                .flat_map(|y| (block_x..block_x + SOURCE_BLOCK).map(move |x| (x, y)))
+                .max_by(|&(ax, ay), &(bx, by)| {
+                    let a = overview.height_at(ax as usize, ay as usize);
+                    let b = overview.height_at(bx as usize, by as usize);
+                    a.total_cmp(&b)
+                });
+            let Some((x, y)) = highest else {
+                continue;
+            };
+            let height = overview.height_at(x as usize, y as usize);
+            if height > sea_level + MIN_SOURCE_HEIGHT
+                && overview.moisture_at(x as usize, y as usize) > MIN_SOURCE_MOISTURE
+            {
+                sources.push((x, y));
+            }
+        }
+    }
+    sources.sort_by(|&(ax, ay), &(bx, by)| {
+        let a = overview.height_at(ax as usize, ay as usize);
+        let b = overview.height_at(bx as usize, by as usize);
+        b.total_cmp(&a)
+    });
+
+    let mut visited = vec![false; samples * samples];
+    let mut rivers = Vec::new();
+    for (x, y) in sources {
+        let mut path = vec![(x, y)];
+        let mut current = (x, y);
+        loop {
+            let i = index(current.0, current.1);
+            let joined = visited[i];
+            visited[i] = true;
+            if joined || overview.height_at(current.0 as usize, current.1 as usize) <= sea_level {
+                break;
+            }
+            match flow[i] {
+                Some(next) => {
+                    path.push(next);
+                    current = next;
+                }
+                None => break,
+            }
+        }
+        if path.len() < MIN_RIVER_LENGTH {
+            continue;
+        }
+
+        let control: Vec<RiverPoint> = path
+            .iter()
+            .map(|&(x, y)| {
+                let cell = (first + IVec2::new(x, y)) * RIVER_STEP as i32;
+                let height = overview.height_at(x as usize, y as usize) as f32
+                    * overview.height_multiplier();
+                RiverPoint {
+                    position: Vec2::new(cell.x as f32, -cell.y as f32),
+                    height: height - WATER_DROP,
+                    width: (1.0 + accumulation[index(x, y)].sqrt() * 0.4).min(10.0),
+                }
+            })
+            .collect();
+        rivers.push(River::new(smooth(&control)));
+    }
+    rivers
+}
+
+const NEIGHBORS: [(i32, i32); 8] = [
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (-1, 0),
+    (1, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+];
+
+/// Catmull-Rom spline through the traced samples, with heights and widths interpolated
+/// linearly so the water never flows uphill.
+fn smooth(control: &[RiverPoint]) -> Vec<RiverPoint> {
+    let mut points = Vec::new();
+    for i in 0..control.len() - 1 {
+        let p0 = control[i.saturating_sub(1)].position;
+        let p1 = control[i].position;
+        let p2 = control[i + 1].position;
+        let p3 = control[(i + 2).min(control.len() - 1)].position;
+
+        for step in 0..SPLINE_SUBDIVISIONS {
+            let t = step as f32 / SPLINE_SUBDIVISIONS as f32;
+            let (t2, t3) = (t * t, t * t * t);
+            let position = 0.5
+                * (2.0 * p1
+                    + (p2 - p0) * t
+                    + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t2
+                    + (3.0 * p1 - p0 - 3.0 * p2 + p3) * t3);
+            points.push(RiverPoint {
+                position,
+                height: control[i].height + (control[i + 1].height - control[i].height) * t,
+                width: control[i].width + (control[i + 1].width - control[i].width) * t,
+            });
+        }
+    }
+    points.extend(control.last().copied());
+    points
+}
+
+/// Distance from `point` to the segment `a`-`b` and how far along it the closest point is.
+pub(super) fn segment_distance(point: Vec2, a: Vec2, b: Vec2) -> (f32, f32) {
+    let ab = b - a;
+    let length = ab.length_squared();
+    let t = if length > 0.0 {
+        ((point - a).dot(ab) / length).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    (point.distance(a + ab * t), t)
+}
+
+/// Cuts river channels and banks into a chunk's map and makes the land around them wetter.
+pub fn carve(map: &mut Noise<Generated>, rivers: &[&River]) {
+    if rivers.is_empty() {
+        return;
+    }
+    let height_multiplier = map.height_multiplier();
+    let mut moisture = vec![0.0_f32; map.width * map.height];
+
+    for river in rivers {
+        for segment in river.points.windows(2) {
+            let (a, b) = (segment[0], segment[1]);
+            let reach = a.width.max(b.width) / 2.0 + BANK_WIDTH + MOISTURE_REACH;
+            let min = a.position.min(b.position) - reach;
+            let max = a.position.max(b.position) + reach;
+
+            // World `(x, z)` is global cell `(x, -z)`.
+            let first_x = (min.x.floor() as i32 - map.origin.x).max(0);
+            let last_x = (max.x.ceil() as i32 - map.origin.x).min(map.width as i32 - 1);
+            let first_y = (-max.y.ceil() as i32 - map.origin.y).max(0);
+            let last_y = (-min.y.floor() as i32 - map.origin.y).min(map.height as i32 - 1);
+
+            for y in first_y..=last_y {
+                for x in first_x..=last_x {
+                    let world = Vec2::new((map.origin.x + x) as f32, -(map.origin.y + y) as f32);
+                    let (distance, t) = segment_distance(world, a.position, b.position);
+                    let half_width = (a.width + (b.width - a.width) * t) / 2.0;
+                    let water = a.height + (b.height - a.height) * t;
+
+                    let target = if distance < half_width {
+                        let depth = 1.0 - (distance / half_width).powi(2);
+                        Some(water - RIVER_DEPTH * depth - 0.1)
+                    } else if distance < half_width + BANK_WIDTH {
+                        Some(water + (distance - half_width) * 0.5)
+                    } else {
+                        None
+                    };
+
+                    let index = x as usize + y as usize * map.width;
+                    if let Some(target) = target {
+                        let target = (target / height_multiplier) as f64;
+                        map.noise_map[index] = map.noise_map[index].min(target.max(0.0));
+                    }
+
+                    let wet = 1.0 - ((distance - half_width).max(0.0) / MOISTURE_REACH);
+                    moisture[index] = moisture[index].max(wet.clamp(0.0, 1.0) * MOISTURE_BONUS);
+                }
+            }
+        }
+    }
+
+    map.river_moisture = moisture;
+}
+
+impl Noise<Generated> {
+    /// Whether a map position is in a river's channel or on its banks.
+    pub fn in_river(&self, x: usize, y: usize) -> bool {
+        // Moisture falls off linearly from the water's edge, so it tells how far away it is.
+        let bank_edge = MOISTURE_BONUS * (1.0 - BANK_WIDTH / MOISTURE_REACH);
+        self.river_moisture
+            .get(x + y * self.width)
+            .is_some_and(|&wet| wet > bank_edge)
+    }
+}
+
+/// Water surface ribbon for the parts of rivers that start inside a chunk, relative to
+/// the chunk's origin.
+pub fn ribbon_mesh(map: &Noise<Generated>, rivers: &[&River]) -> Option<Mesh> {
+    let origin = map.world_origin();
+    let size = (map.width - 1) as f32;
+    // Each chunk only draws segments starting in the cells it owns, which grow towards
+    // world -Z, so borders aren't drawn twice.
+    let owns = |point: Vec2| {
+        point.x >= origin.x
+            && point.x < origin.x + size
+            && point.y <= origin.z
+            && point.y > origin.z - size
+    };
+
+    let mut positions = Vec::new();
+    let mut indices = Vec::new();
+    for river in rivers {
+        for segment in river.points.windows(2) {
+            let (a, b) = (segment[0], segment[1]);
+            if !owns(a.position) {
+                continue;
+            }
+            let direction = (b.position - a.position).normalize_or_zero();
+            let side = Vec2::new(-direction.y, direction.x);
+
+            let first = positions.len() as u32;
+            for point in [a, b] {
+                for sign in [-1.0, 1.0] {
+                    let edge = point.position + side * sign * point.width / 2.0;
+                    positions.push(Vec3::new(edge.x, point.height, edge.y) - origin);
+                }
+            }
+            indices.extend([first, first + 2, first + 1, first + 1, first + 2, first + 3]);
+        }
+    }
+    if positions.is_empty() {
+        return None;
+    }
+
+    let normals = vec![Vec3::Y; positions.len()];
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    mesh.set_indices(Some(Indices::U32(indices)));
+    Some(mesh)
+}
+
+#[derive(Resource)]
+pub struct RiverMaterial(pub Handle<StandardMaterial>);
+
+impl FromWorld for RiverMaterial {
+    fn from_world(world: &mut World) -> Self {
+        let mut materials = world.resource_mut::<Assets<StandardMaterial>>();
+        Self(materials.add(StandardMaterial {
+            base_color: RIVER_COLOR,
+            alpha_mode: AlphaMode::Blend,
+            perceptual_roughness: 0.1,
+            // Ribbons of rivers in opposite directions face the same way, but stay visible.
+            cull_mode: None,
+            ..Default::default()
+        }))
+    }
+}
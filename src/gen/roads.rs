@@ -0,0 +1,421 @@
+//! Roads linking every structure to its nearest neighbors.
+//!
+//! Each road is found with A* over a coarse overview of the terrain between its two ends,
+//! where steep steps cost more than flat ones and the sea is avoided. Everything depends
+//! only on the seed, so every chunk a road crosses finds the same road and paves its own
+//! part of it.
+
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+use bevy::{prelude::*, utils::HashMap};
+
+use super::{
+    chunk::ChunkCoord,
+    river::{segment_distance, River, Rivers, BANK_WIDTH},
+    scatter::Placement,
+    structures::{Structure, Structures},
+    Generated, Noise, NoiseConfig,
+};
+
+/// Global cells between two nodes of the path search.
+const ROAD_STEP: usize = 4;
+/// Farthest apart two structures can be and still get a road between them, in cells.
+const ROAD_REACH: f32 = 480.0;
+/// How many of its nearest neighbors each structure is linked to.
+const ROAD_NEIGHBORS: usize = 2;
+/// Cells around the two ends the path search may wander into.
+const SEARCH_MARGIN: i32 = 64;
+const ROAD_WIDTH: f32 = 3.0;
+/// Cells between a road's edge and where the terrain is untouched again.
+const ROAD_BLEND: f32 = 4.0;
+/// How much a step's cost grows with the square of its grade.
+const SLOPE_COST: f32 = 40.0;
+/// Extra cost per cell of going through the sea.
+const WATER_COST: f32 = 50.0;
+/// Extra cost per cell of crossing a river or its banks, so roads ford them where narrow.
+const RIVER_COST: f32 = 20.0;
+/// Path nodes averaged on each side of a node to even out the road's grade.
+const GRADE_SMOOTHING: usize = 3;
+
+#[derive(Clone, Copy, Debug)]
+pub struct RoadPoint {
+    /// World position on the XZ plane.
+    pub position: Vec2,
+    /// World height of the road surface.
+    pub height: f32,
+}
+
+#[derive(Clone, Debug)]
+pub struct Road {
+    pub points: Vec<RoadPoint>,
+    min: Vec2,
+    max: Vec2,
+}
+
+impl Road {
+    fn new(points: Vec<RoadPoint>) -> Self {
+        let reach = ROAD_WIDTH / 2.0 + ROAD_BLEND;
+        let min = points.iter().fold(Vec2::MAX, |min, p| min.min(p.position)) - reach;
+        let max = points.iter().fold(Vec2::MIN, |max, p| max.max(p.position)) + reach;
+        Self { points, min, max }
+    }
+
+    /// Whether anything of the road, including its shoulders, reaches into the rectangle.
+    pub fn overlaps(&self, min: Vec2, max: Vec2) -> bool {
+        self.min.cmple(max).all() && self.max.cmpge(min).all()
+    }
+}
+
+/// Roads found so far by the centers of the structures they link, `None` if there is no
+/// way between them.
+#[derive(Resource, Default)]
+pub struct Roads {
+    roads: HashMap<(IVec2, IVec2), Option<Road>>,
+}
+
+impl Roads {
+    pub fn clear(&mut self) {
+        self.roads.clear();
+    }
+
+    /// Roads that could reach into a chunk, finding them if needed.
+    ///
+    /// At most `searches` roads are found, and it's lowered by how many were. Returns
+    /// `None` if the chunk needs more than that, the roads found so far are kept for the
+    /// next try.
+    pub fn near_chunk(
+        &mut self,
+        config: &NoiseConfig,
+        structures: &mut Structures,
+        rivers: &mut Rivers,
+        coord: ChunkCoord,
+        searches: &mut usize,
+    ) -> Option<Vec<&Road>> {
+        let origin = coord.origin(config.chunk_size());
+        let size = config.chunk_size() as i32;
+        // A road stays within the search margin around its two ends, which are at most
+        // the reach apart.
+        let reach = (ROAD_REACH + ROAD_WIDTH / 2.0 + ROAD_BLEND).ceil() as i32 + SEARCH_MARGIN;
+        let ends = structures.near_cells(config, rivers, origin, origin + size, reach);
+
+        let mut links = Vec::new();
+        for end in &ends {
+            for neighbor in nearest_neighbors(config, structures, rivers, end) {
+                // A* can break ties differently each way, so roads are always found from
+                // the lower end whichever chunk asks first.
+                let (from, to) =
+                    match (end.center.x, end.center.y) < (neighbor.center.x, neighbor.center.y) {
+                        true => (end, &neighbor),
+                        false => (&neighbor, end),
+                    };
+                let key = (from.center, to.center);
+                links.push(key);
+                if !self.roads.contains_key(&key) {
+                    if *searches == 0 {
+                        return None;
+                    }
+                    *searches -= 1;
+                    self.roads.insert(key, find_road(config, rivers, from, to));
+                }
+            }
+        }
+
+        // Paving order decides the height where roads meet, so every chunk sorts them.
+        links.sort_by_key(|(a, b)| (a.x, a.y, b.x, b.y));
+        links.dedup();
+
+        // One more cell for the apron chunks are generated with.
+        let min = Vec2::new(origin.x as f32, -(origin.y + size) as f32) - 1.0;
+        let max = Vec2::new((origin.x + size) as f32, -origin.y as f32) + 1.0;
+        let roads = links
+            .iter()
+            .filter_map(|key| self.roads[key].as_ref())
+            .filter(|road| road.overlaps(min, max))
+            .collect();
+        Some(roads)
+    }
+}
+
+/// The structures within reach a structure is linked to, closest first.
+fn nearest_neighbors(
+    config: &NoiseConfig,
+    structures: &mut Structures,
+    rivers: &mut Rivers,
+    structure: &Structure,
+) -> Vec<Structure> {
+    let center = structure.center;
+    let mut neighbors: Vec<Structure> = structures
+        .near_cells(config, rivers, center, center, ROAD_REACH as i32)
+        .into_iter()
+        .filter(|other| other.center != center)
+        .collect();
+    // Ties go to the lower cell so every chunk links the same structures.
+    neighbors.sort_by_key(|other| {
+        let offset = other.center - center;
+        (offset.length_squared(), other.center.x, other.center.y)
+    });
+    neighbors.truncate(ROAD_NEIGHBORS);
+    neighbors
+}
+
+const NEIGHBORS: [(i32, i32); 8] = [
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (-1, 0),
+    (1, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+];
+
+/// A* from one structure to another over a coarse overview of the terrain between them.
+fn find_road(
+    config: &NoiseConfig,
+    rivers: &mut Rivers,
+    from: &Structure,
+    to: &Structure,
+) -> Option<Road> {
+    let first = from.center.min(to.center) - SEARCH_MARGIN;
+    let last = from.center.max(to.center) + SEARCH_MARGIN;
+    let width = ((last.x - first.x) as usize).div_ceil(ROAD_STEP) + 1;
+    let height = ((last.y - first.y) as usize).div_ceil(ROAD_STEP) + 1;
+    let overview = Noise::from(config)
+        .with_origin(first)
+        .with_step(ROAD_STEP)
+        .with_size(width, height)
+        .generate_map();
+    let sea_level = overview.sea_level();
+    let height_multiplier = overview.height_multiplier();
+    let river_nodes = river_nodes(
+        &rivers.near_cells(config, first, last),
+        first,
+        width,
+        height,
+    );
+
+    let node_of = |cell: IVec2| {
+        let node = ((cell - first).as_vec2() / ROAD_STEP as f32)
+            .round()
+            .as_ivec2();
+        node.x as usize + node.y as usize * width
+    };
+    let cell_of = |node: usize| {
+        first + IVec2::new((node % width) as i32, (node / width) as i32) * ROAD_STEP as i32
+    };
+    let (start, goal) = (node_of(from.center), node_of(to.center));
+    let goal_cell = cell_of(goal).as_vec2();
+    // Costs are kept in hundredths so they can be ordered in the heap.
+    let estimate = |node: usize| (cell_of(node).as_vec2().distance(goal_cell) * 100.0) as u64;
+
+    let mut costs = vec![u64::MAX; width * height];
+    let mut came_from = vec![usize::MAX; width * height];
+    let mut open = BinaryHeap::new();
+    costs[start] = 0;
+    open.push(Reverse((estimate(start), start)));
+
+    while let Some(Reverse((_, node))) = open.pop() {
+        if node == goal {
+            break;
+        }
+        let (x, y) = ((node % width) as i32, (node / width) as i32);
+        let node_height = overview.noise_map[node];
+
+        for (dx, dy) in NEIGHBORS {
+            let (nx, ny) = (x + dx, y + dy);
+            if nx < 0 || ny < 0 || nx >= width as i32 || ny >= height as i32 {
+                continue;
+            }
+            let next = nx as usize + ny as usize * width;
+            let next_height = overview.noise_map[next];
+
+            let run = ((dx * dx + dy * dy) as f32).sqrt() * ROAD_STEP as f32;
+            let rise = (next_height - node_height).abs() as f32 * height_multiplier;
+            let grade = rise / run;
+            let mut cost = run * (1.0 + SLOPE_COST * grade * grade);
+            if next_height <= sea_level {
+                cost += run * WATER_COST;
+            }
+            if river_nodes[next] {
+                cost += run * RIVER_COST;
+            }
+
+            let cost = costs[node] + (cost * 100.0) as u64;
+            if cost < costs[next] {
+                costs[next] = cost;
+                came_from[next] = node;
+                open.push(Reverse((cost + estimate(next), next)));
+            }
+        }
+    }
+    if costs[goal] == u64::MAX {
+        return None;
+    }
+
+    let mut path = vec![goal];
+    while let Some(&node) = path.last() {
+        if node == start {
+            break;
+        }
+        path.push(came_from[node]);
+    }
+    path.reverse();
+
+    // Evens out the grade, then pins both ends to the ground of their structures.
+    let heights: Vec<f64> = (0..path.len())
+        .map(|i| {
+            let window =
+                &path[i.saturating_sub(GRADE_SMOOTHING)..(i + GRADE_SMOOTHING + 1).min(path.len())];
+            window
+                .iter()
+                .map(|&node| overview.noise_map[node])
+                .sum::<f64>()
+                / window.len() as f64
+        })
+        .collect();
+    let last_index = path.len() - 1;
+    let points: Vec<RoadPoint> = path
+        .iter()
+        .zip(heights)
+        .enumerate()
+        .map(|(i, (&node, height))| {
+            let cell = match i {
+                0 => from.center,
+                i if i == last_index => to.center,
+                _ => cell_of(node),
+            };
+            let height = match i {
+                0 => from.height,
+                i if i == last_index => to.height,
+                _ => height,
+            };
+            RoadPoint {
+                position: Vec2::new(cell.x as f32, -cell.y as f32),
+                height: height as f32 * height_multiplier,
+            }
+        })
+        .collect();
+
+    Some(Road::new(smooth(points)))
+}
+
+/// Marks the nodes of an overview starting at global cell `first` that a river's water or
+/// banks cover.
+fn river_nodes(rivers: &[&River], first: IVec2, width: usize, height: usize) -> Vec<bool> {
+    let mut covered = vec![false; width * height];
+    let step = ROAD_STEP as f32;
+
+    for river in rivers {
+        for segment in river.points.windows(2) {
+            let (a, b) = (segment[0], segment[1]);
+            // Half a step more so narrow rivers between two nodes aren't missed.
+            let reach = a.width.max(b.width) / 2.0 + BANK_WIDTH + step / 2.0;
+            let min = a.position.min(b.position) - reach;
+            let max = a.position.max(b.position) + reach;
+
+            // World `(x, z)` is global cell `(x, -z)`.
+            let node = |cell: f32, first: i32| (cell - first as f32) / step;
+            let first_x = node(min.x, first.x).floor().max(0.0) as usize;
+            let last_x = (node(max.x, first.x).ceil().max(0.0) as usize).min(width - 1);
+            let first_y = node(-max.y, first.y).floor().max(0.0) as usize;
+            let last_y = (node(-min.y, first.y).ceil().max(0.0) as usize).min(height - 1);
+
+            for y in first_y..=last_y {
+                for x in first_x..=last_x {
+                    let cell = first + IVec2::new(x as i32, y as i32) * ROAD_STEP as i32;
+                    let world = Vec2::new(cell.x as f32, -cell.y as f32);
+                    if segment_distance(world, a.position, b.position).0 <= reach {
+                        covered[x + y * width] = true;
+                    }
+                }
+            }
+        }
+    }
+    covered
+}
+
+/// Rounds off the corners of the grid path by cutting each of them twice.
+fn smooth(mut points: Vec<RoadPoint>) -> Vec<RoadPoint> {
+    for _ in 0..2 {
+        if points.len() < 3 {
+            break;
+        }
+        let mut cut = vec![points[0]];
+        for pair in points.windows(2) {
+            let (a, b) = (pair[0], pair[1]);
+            for t in [0.25, 0.75] {
+                cut.push(RoadPoint {
+                    position: a.position.lerp(b.position, t),
+                    height: a.height + (b.height - a.height) * t,
+                });
+            }
+        }
+        cut.extend(points.last().copied());
+        points = cut;
+    }
+    points
+}
+
+/// Levels the terrain along roads and marks the cells they cover for coloring.
+///
+/// Has to run after [`river::carve`](super::river::carve), whose channels it leaves as deep
+/// as they are.
+pub fn pave(map: &mut Noise<Generated>, roads: &[&Road]) {
+    if roads.is_empty() {
+        return;
+    }
+    let height_multiplier = map.height_multiplier();
+    let half_width = ROAD_WIDTH / 2.0;
+    let mut mask = vec![0.0_f32; map.width * map.height];
+
+    for road in roads {
+        for segment in road.points.windows(2) {
+            let (a, b) = (segment[0], segment[1]);
+            let reach = half_width + ROAD_BLEND;
+            let min = a.position.min(b.position) - reach;
+            let max = a.position.max(b.position) + reach;
+
+            // World `(x, z)` is global cell `(x, -z)`.
+            let first_x = (min.x.floor() as i32 - map.origin.x).max(0);
+            let last_x = (max.x.ceil() as i32 - map.origin.x).min(map.width as i32 - 1);
+            let first_y = (-max.y.ceil() as i32 - map.origin.y).max(0);
+            let last_y = (-min.y.floor() as i32 - map.origin.y).min(map.height as i32 - 1);
+
+            for y in first_y..=last_y {
+                for x in first_x..=last_x {
+                    let world = Vec2::new((map.origin.x + x) as f32, -(map.origin.y + y) as f32);
+                    let (distance, t) = segment_distance(world, a.position, b.position);
+                    if distance > reach {
+                        continue;
+                    }
+
+                    let t_blend = (1.0 - (distance - half_width) / ROAD_BLEND).clamp(0.0, 1.0);
+                    let weight = (t_blend * t_blend * (3.0 - 2.0 * t_blend)) as f64;
+                    let target =
+                        ((a.height + (b.height - a.height) * t) / height_multiplier) as f64;
+
+                    let index = x as usize + y as usize * map.width;
+                    let height = map.noise_map[index];
+                    let mut paved = height + (target - height) * weight;
+                    // Roads ford rivers, filling in the channel would bury the water.
+                    if map.in_river(x as usize, y as usize) {
+                        paved = paved.min(height);
+                    }
+                    map.noise_map[index] = paved;
+                    mask[index] = mask[index].max((half_width + 0.5 - distance).clamp(0.0, 1.0));
+                }
+            }
+        }
+    }
+
+    map.road_mask = mask;
+}
+
+/// Drops scattered objects that would end up on a road.
+pub fn clear_roads(placements: &mut Vec<Placement>, map: &Noise<Generated>) {
+    placements.retain(|placement| {
+        let x = placement.position.x.round().max(0.0) as usize;
+        let y = (-placement.position.z).round().max(0.0) as usize;
+        map.road_at(x.min(map.width - 1), y.min(map.height - 1)) < 0.5
+    });
+}
@@ -0,0 +1,232 @@
+use std::f32::consts::{SQRT_2, TAU};
+
+use bevy::prelude::*;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use super::{
+    biome::{Biome, MEAN_TEMPERATURE},
+    chunk::ChunkCoord,
+    Generated, Noise,
+};
+
+/// Candidates tried around each active sample before it is retired.
+const POISSON_ATTEMPTS: usize = 30;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ScatterKind {
+    Tree,
+    Bush,
+    Rock,
+    Grass,
+}
+
+impl ScatterKind {
+    pub const ALL: [ScatterKind; 4] = [
+        ScatterKind::Tree,
+        ScatterKind::Bush,
+        ScatterKind::Rock,
+        ScatterKind::Grass,
+    ];
+
+    /// Smallest distance between two objects of this kind, in world units.
+    pub fn spacing(self) -> f32 {
+        match self {
+            ScatterKind::Tree => 4.0,
+            ScatterKind::Bush => 3.0,
+            ScatterKind::Rock => 5.0,
+            ScatterKind::Grass => 1.0,
+        }
+    }
+
+    /// Steepest slope, in height per cell, this kind is still placed on.
+    pub fn max_slope(self) -> f32 {
+        match self {
+            ScatterKind::Tree => 0.8,
+            ScatterKind::Bush => 1.0,
+            ScatterKind::Rock => 2.0,
+            ScatterKind::Grass => 0.6,
+        }
+    }
+
+    /// Whether the kind grows at a normalized `height`, given the map's sea and snow lines.
+    pub fn fits_height(self, height: f64, sea_level: f64, snow_line: f64) -> bool {
+        match self {
+            ScatterKind::Rock => height > sea_level,
+            ScatterKind::Tree | ScatterKind::Bush => {
+                height > sea_level + 0.02 && height < snow_line
+            }
+            ScatterKind::Grass => height > sea_level + 0.03 && height < snow_line,
+        }
+    }
+}
+
+impl Biome {
+    /// Fraction of Poisson-disk samples kept for each kind in the biome.
+    pub fn scatter_density(self, kind: ScatterKind) -> f32 {
+        use ScatterKind::*;
+
+        match (self, kind) {
+            (Biome::Ocean, _) => 0.0,
+            (Biome::Beach, Rock) => 0.1,
+            (Biome::Beach, Grass) => 0.1,
+            (Biome::Desert, Rock) => 0.2,
+            (Biome::Desert, Bush) => 0.05,
+            (Biome::Grassland, Tree) => 0.08,
+            (Biome::Grassland, Bush) => 0.2,
+            (Biome::Grassland, Rock) => 0.05,
+            (Biome::Grassland, Grass) => 0.6,
+            (Biome::Forest, Tree) => 0.6,
+            (Biome::Forest, Bush) => 0.2,
+            (Biome::Forest, Rock) => 0.05,
+            (Biome::Forest, Grass) => 0.3,
+            (Biome::Taiga, Tree) => 0.45,
+            (Biome::Taiga, Rock) => 0.1,
+            (Biome::Taiga, Grass) => 0.2,
+            (Biome::Tundra, Rock) => 0.15,
+            (Biome::Tundra, Bush) => 0.05,
+            (Biome::Tundra, Grass) => 0.15,
+            (Biome::Snow, Rock) => 0.1,
+            _ => 0.0,
+        }
+    }
+}
+
+/// A single scattered object.
+#[derive(Clone, Copy, Debug)]
+pub struct Placement {
+    pub kind: ScatterKind,
+    /// Position on the terrain surface relative to the chunk's world origin.
+    pub position: Vec3,
+    /// Rotation around the Y axis, in radians.
+    pub rotation: f32,
+    pub scale: f32,
+}
+
+/// Scatters every [`ScatterKind`] over a chunk's map.
+///
+/// The result only depends on the seed, the chunk coordinate and the map, so a chunk that
+/// is unloaded and generated again gets the same placements. Each kind draws from its own
+/// generator so tuning one of them doesn't move the others.
+pub fn scatter_chunk(map: &Noise<Generated>, coord: ChunkCoord) -> Vec<Placement> {
+    let size = (map.width().min(map.height()) - 1) as f32;
+    let sea_level = map.sea_level();
+    let mut placements = Vec::new();
+
+    for (salt, kind) in ScatterKind::ALL.into_iter().enumerate() {
+        let mut rng = grid_rng(map.seed(), coord.0, salt as u64);
+
+        for point in poisson_disk(&mut rng, size, kind.spacing()) {
+            // Always roll so one rejected sample doesn't shift the rest of the chunk.
+            let roll: f32 = rng.gen();
+            let rotation = rng.gen_range(0.0..TAU);
+            let scale = rng.gen_range(0.8..1.2);
+
+            let (x, y) = (point.x.round() as usize, point.y.round() as usize);
+            let height = map.sample_height(point.x, point.y);
+            if !kind.fits_height(height, sea_level, map.snow_line)
+                || map.slope_at(x, y) > kind.max_slope()
+            {
+                continue;
+            }
+            if roll >= map.biome_at(x, y, MEAN_TEMPERATURE).scatter_density(kind) {
+                continue;
+            }
+
+            placements.push(Placement {
+                kind,
+                position: Vec3::new(point.x, height as f32 * map.height_multiplier(), -point.y),
+                rotation,
+                scale,
+            });
+        }
+    }
+
+    placements
+}
+
+/// Moves placements back onto the surface after the map's heights changed.
+pub fn settle(placements: &mut [Placement], map: &Noise<Generated>) {
+    for placement in placements {
+        let height = map.sample_height(placement.position.x, -placement.position.z);
+        placement.position.y = height as f32 * map.height_multiplier();
+    }
+}
+
+/// Generator seeded from the world seed, a cell of some grid and a per-use salt.
+pub(super) fn grid_rng(seed: u32, cell: IVec2, salt: u64) -> StdRng {
+    let mut hash = seed as u64;
+    for value in [cell.x as u64, cell.y as u64, salt] {
+        hash = splitmix64(hash ^ value);
+    }
+    StdRng::seed_from_u64(hash)
+}
+
+fn splitmix64(value: u64) -> u64 {
+    let mut z = value.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// Bridson's Poisson-disk sampling over a `size` by `size` square.
+///
+/// Points are at least `radius` apart within the square. Neighboring chunks are sampled
+/// independently, so points on either side of a border can end up closer than that.
+pub fn poisson_disk(rng: &mut StdRng, size: f32, radius: f32) -> Vec<Vec2> {
+    let cell_size = radius / SQRT_2;
+    let grid_size = (size / cell_size).ceil() as usize;
+    let mut grid: Vec<Option<usize>> = vec![None; grid_size * grid_size];
+    let cell_of = |point: Vec2| {
+        let x = ((point.x / cell_size) as usize).min(grid_size - 1);
+        let y = ((point.y / cell_size) as usize).min(grid_size - 1);
+        (x, y)
+    };
+
+    let mut points = Vec::new();
+    let mut active = Vec::new();
+
+    let first = Vec2::new(rng.gen_range(0.0..size), rng.gen_range(0.0..size));
+    let (x, y) = cell_of(first);
+    grid[x + y * grid_size] = Some(0);
+    points.push(first);
+    active.push(0);
+
+    while !active.is_empty() {
+        let index = rng.gen_range(0..active.len());
+        let origin = points[active[index]];
+        let mut found = false;
+
+        for _ in 0..POISSON_ATTEMPTS {
+            let angle = rng.gen_range(0.0..TAU);
+            let distance = rng.gen_range(radius..radius * 2.0);
+            let candidate = origin + Vec2::from_angle(angle) * distance;
+            if candidate.x < 0.0 || candidate.y < 0.0 || candidate.x >= size || candidate.y >= size
+            {
+                continue;
+            }
+
+            let (x, y) = cell_of(candidate);
+            let too_close = (y.saturating_sub(2)..(y + 3).min(grid_size))
+                .flat_map(|ny| {
+                    (x.saturating_sub(2)..(x + 3).min(grid_size)).map(move |nx| (nx, ny))
+                })
+                .filter_map(|(nx, ny)| grid[nx + ny * grid_size])
+                .any(|other| points[other].distance_squared(candidate) < radius * radius);
+            if too_close {
+                continue;
+            }
+
+            grid[x + y * grid_size] = Some(points.len());
+            active.push(points.len());
+            points.push(candidate);
+            found = true;
+            break;
+        }
+
+        if !found {
+            active.swap_remove(index);
+        }
+    }
+
+    points
+}
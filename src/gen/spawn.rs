@@ -0,0 +1,85 @@
+//! Finding safe ground on the loaded terrain to put the player on.
+
+use bevy::prelude::*;
+
+use super::{
+    chunk::{ChunkCoord, Terrain},
+    structures::MIN_SHORE_HEIGHT,
+    Generated, Noise,
+};
+
+pub enum SpawnSearch {
+    /// Where the player can be placed.
+    Found(Vec3),
+    /// Part of the searched area isn't loaded yet.
+    Pending,
+    /// Nowhere in the searched area is safe.
+    Nothing,
+}
+
+impl Noise<Generated> {
+    /// Whether a map position is dry land, away from rivers and no steeper than
+    /// `max_slope` world units per cell.
+    pub fn is_safe_ground(&self, x: usize, y: usize, max_slope: f32) -> bool {
+        self.height_at(x, y) > self.sea_level() + MIN_SHORE_HEIGHT
+            && !self.in_river(x, y)
+            && self.slope_at(x, y) <= max_slope
+    }
+}
+
+impl Terrain {
+    /// Searches the cells within `radius` of a world position on the XZ plane, closest
+    /// first, for safe ground.
+    ///
+    /// The surface position of every safe cell is handed to `place`, which returns where
+    /// the player actually goes or `None` to keep searching. The whole area has to be
+    /// loaded, so whatever waits on the search should hold a
+    /// [`ChunkAnchor`](super::chunk::ChunkAnchor) over it.
+    pub fn find_spawn(
+        &self,
+        point: Vec2,
+        radius: u32,
+        max_slope: f32,
+        mut place: impl FnMut(Vec3) -> Option<Vec3>,
+    ) -> SpawnSearch {
+        if self.chunk_size == 0 {
+            return SpawnSearch::Pending;
+        }
+
+        // World `(x, z)` is global cell `(x, -z)`.
+        let center = IVec2::new(point.x.round() as i32, (-point.y).round() as i32);
+        let radius = radius as i32;
+        let size = self.chunk_size as i32;
+        let chunk_of = |cell: IVec2| IVec2::new(cell.x.div_euclid(size), cell.y.div_euclid(size));
+        let (first, last) = (chunk_of(center - radius), chunk_of(center + radius));
+        let loaded = (first.y..=last.y)
+            .flat_map(|y| (first.x..=last.x).map(move |x| IVec2::new(x, y)))
+            .all(|coord| self.chunks.contains_key(&ChunkCoord(coord)));
+        if !loaded {
+            return SpawnSearch::Pending;
+        }
+
+        let mut offsets: Vec<IVec2> = (-radius..=radius)
+            .flat_map(|y| (-radius..=radius).map(move |x| IVec2::new(x, y)))
+            .collect();
+        offsets.sort_by_key(|offset| (offset.length_squared(), offset.x, offset.y));
+
+        for offset in offsets {
+            let cell = center + offset;
+            let position = Vec2::new(cell.x as f32, -cell.y as f32);
+            let Some(map) = self.chunk_at(position).map(|chunk| &chunk.map) else {
+                continue;
+            };
+            let Some((x, y)) = map.world_to_map(position) else {
+                continue;
+            };
+            if !map.is_safe_ground(x, y, max_slope) {
+                continue;
+            }
+            if let Some(found) = place(map.map_to_world(x, y)) {
+                return SpawnSearch::Found(found);
+            }
+        }
+        SpawnSearch::Nothing
+    }
+}
@@ -0,0 +1,245 @@
+//! Terrain material blending tiled layer textures by a per-chunk splat map.
+//!
+//! Each chunk gets an image the size of its map whose channels are the weights of the
+//! sand, grass, rock and snow layers at every cell. The shader in
+//! `assets/shaders/terrain.wgsl` samples the layers with triplanar projection so steep
+//! slopes don't stretch them, and mixes the vertex colors back in for tints.
+
+use bevy::{
+    pbr::{ExtendedMaterial, MaterialExtension},
+    prelude::*,
+    render::{
+        render_resource::{
+            AsBindGroup, Extent3d, ShaderRef, ShaderType, TextureDimension, TextureFormat,
+        },
+        texture::{ImageAddressMode, ImageSampler, ImageSamplerDescriptor},
+    },
+};
+use noise::{NoiseFn, Perlin};
+
+use super::{
+    biome::{Biome, MEAN_TEMPERATURE},
+    DrawMode, Generated, Noise, WATER_REGION,
+};
+
+pub type TerrainMaterial = ExtendedMaterial<StandardMaterial, TerrainExtension>;
+
+/// Pixels along each side of a generated layer texture.
+const LAYER_SIZE: u32 = 256;
+/// World units covered by one repeat of the layer textures.
+const TEXTURE_SCALE: f32 = 8.0;
+/// How much of the vertex color is mixed into the layers, so seasonal tints still show.
+const VERTEX_COLOR_MIX: f32 = 0.3;
+/// Slopes in world units per cell between which ground turns into bare rock.
+const ROCK_SLOPE: (f32, f32) = (0.8, 1.6);
+/// Normalized height over which snow fades in below the snow line.
+const SNOW_FADE: f64 = 0.03;
+
+#[derive(Clone, Copy, Debug, ShaderType, Reflect)]
+pub struct TerrainUniform {
+    /// World position of the chunk's first cell on the XZ plane.
+    pub origin: Vec2,
+    /// Cells along each side of the splat map.
+    pub size: f32,
+    pub texture_scale: f32,
+    pub vertex_color_mix: f32,
+}
+
+#[derive(Asset, AsBindGroup, Reflect, Debug, Clone)]
+pub struct TerrainExtension {
+    // Slots below 100 belong to the `StandardMaterial` this extends.
+    #[uniform(100)]
+    pub settings: TerrainUniform,
+    #[texture(101)]
+    #[sampler(102)]
+    pub splat: Handle<Image>,
+    // Every layer shares the repeating sampler of the first one.
+    #[texture(103)]
+    #[sampler(104)]
+    pub sand: Handle<Image>,
+    #[texture(105)]
+    pub grass: Handle<Image>,
+    #[texture(106)]
+    pub rock: Handle<Image>,
+    #[texture(107)]
+    pub snow: Handle<Image>,
+}
+
+impl MaterialExtension for TerrainExtension {
+    fn fragment_shader() -> ShaderRef {
+        "shaders/terrain.wgsl".into()
+    }
+}
+
+/// Tiling textures of every layer, shared by all chunks.
+#[derive(Resource)]
+pub struct TerrainLayers {
+    pub sand: Handle<Image>,
+    pub grass: Handle<Image>,
+    pub rock: Handle<Image>,
+    pub snow: Handle<Image>,
+}
+
+impl FromWorld for TerrainLayers {
+    fn from_world(world: &mut World) -> Self {
+        let mut images = world.resource_mut::<Assets<Image>>();
+        Self {
+            sand: images.add(layer_texture(1, Color::rgb(0.76, 0.68, 0.5), 0.12)),
+            grass: images.add(layer_texture(2, Color::rgb(0.3, 0.5, 0.2), 0.2)),
+            rock: images.add(layer_texture(3, Color::rgb(0.45, 0.43, 0.4), 0.3)),
+            snow: images.add(layer_texture(4, Color::rgb(0.93, 0.94, 0.98), 0.05)),
+        }
+    }
+}
+
+impl TerrainLayers {
+    /// Material for a chunk with the splat map already added to the images.
+    pub fn material(&self, map: &Noise<Generated>, splat: Handle<Image>) -> TerrainMaterial {
+        let origin = map.world_origin();
+        // The debug noise view is only readable in its own colors.
+        let vertex_color_mix = match map.draw_mode {
+            DrawMode::NoiseMap => 1.0,
+            _ => VERTEX_COLOR_MIX,
+        };
+
+        ExtendedMaterial {
+            base: StandardMaterial {
+                perceptual_roughness: 0.9,
+                ..Default::default()
+            },
+            extension: TerrainExtension {
+                settings: TerrainUniform {
+                    origin: Vec2::new(origin.x, origin.z),
+                    size: map.width as f32,
+                    texture_scale: TEXTURE_SCALE,
+                    vertex_color_mix,
+                },
+                splat,
+                sand: self.sand.clone(),
+                grass: self.grass.clone(),
+                rock: self.rock.clone(),
+                snow: self.snow.clone(),
+            },
+        }
+    }
+}
+
+/// A seamlessly repeating texture of `color` with noise of up to `variation` in brightness.
+///
+/// The noise is sampled around a torus in 4D so both pairs of opposite edges match.
+fn layer_texture(seed: u32, color: Color, variation: f32) -> Image {
+    let perlin = Perlin::new(seed);
+    let mut data = Vec::with_capacity((LAYER_SIZE * LAYER_SIZE * 4) as usize);
+    for y in 0..LAYER_SIZE {
+        for x in 0..LAYER_SIZE {
+            let (u, v) = (
+                x as f64 / LAYER_SIZE as f64 * std::f64::consts::TAU,
+                y as f64 / LAYER_SIZE as f64 * std::f64::consts::TAU,
+            );
+            let mut value = 0.0;
+            let mut amplitude = 0.5;
+            for radius in [1.0, 2.0, 4.0, 8.0] {
+                let point = [
+                    u.cos() * radius,
+                    u.sin() * radius,
+                    v.cos() * radius,
+                    v.sin() * radius,
+                ];
+                value += perlin.get(point) * amplitude;
+                amplitude *= 0.5;
+            }
+
+            let brightness = 1.0 + value as f32 * variation * 2.0;
+            let [r, g, b, _] = color.as_rgba_f32();
+            data.extend(
+                [r, g, b].map(|channel| ((channel * brightness).clamp(0.0, 1.0) * 255.0) as u8),
+            );
+            data.push(255);
+        }
+    }
+
+    let mut image = Image::new(
+        Extent3d {
+            width: LAYER_SIZE,
+            height: LAYER_SIZE,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+    );
+    image.sampler = ImageSampler::Descriptor(ImageSamplerDescriptor {
+        address_mode_u: ImageAddressMode::Repeat,
+        address_mode_v: ImageAddressMode::Repeat,
+        ..ImageSamplerDescriptor::linear()
+    });
+    image
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+impl Noise<Generated> {
+    /// Weights of the sand, grass, rock and snow layers at a map position, summing to one.
+    pub fn layer_weights(&self, x: usize, y: usize) -> [f32; 4] {
+        let height = self.height_at(x, y);
+        let underwater = self
+            .regions
+            .iter()
+            .find(|region| height <= region.height)
+            .is_some_and(|region| region.name == WATER_REGION);
+
+        let [mut sand, mut grass, mut rock, mut snow]: [f32; 4] =
+            match self.biome_at(x, y, MEAN_TEMPERATURE) {
+                _ if underwater => [1.0, 0.0, 0.0, 0.0],
+                Biome::Ocean | Biome::Beach | Biome::Desert => [1.0, 0.0, 0.0, 0.0],
+                Biome::Grassland | Biome::Forest | Biome::Taiga => [0.0, 1.0, 0.0, 0.0],
+                Biome::Tundra => [0.0, 0.5, 0.5, 0.0],
+                Biome::Snow => [0.0, 0.0, 0.0, 1.0],
+            };
+
+        let snow_line = self.snow_line - self.tint.snow_line_offset;
+        let snowy = ((height - (snow_line - SNOW_FADE)) / SNOW_FADE).clamp(0.0, 1.0) as f32;
+        snow = snow.max(snowy);
+
+        let steep = smoothstep(ROCK_SLOPE.0, ROCK_SLOPE.1, self.slope_at(x, y));
+        rock = rock.max(steep);
+        // Snow doesn't stick to cliffs.
+        snow *= 1.0 - steep;
+
+        // Roads are dirt, which the sand layer stands in for.
+        let road = self.road_at(x, y);
+        sand = sand.max(road);
+        grass *= 1.0 - road;
+
+        let total = sand + grass + rock + snow;
+        match total > 0.0 {
+            true => [sand / total, grass / total, rock / total, snow / total],
+            false => [0.0, 1.0, 0.0, 0.0],
+        }
+    }
+
+    /// Layer weights of every cell, in the same layout as the map.
+    pub fn splat_map(&self) -> Image {
+        let data = (0..self.height)
+            .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+            .flat_map(|(x, y)| {
+                self.layer_weights(x, y)
+                    .map(|weight| (weight * 255.0).round() as u8)
+            })
+            .collect();
+
+        Image::new(
+            Extent3d {
+                width: self.width as u32,
+                height: self.height as u32,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            data,
+            TextureFormat::Rgba8Unorm,
+        )
+    }
+}
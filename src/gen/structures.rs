@@ -0,0 +1,460 @@
+//! Villages, ruins and camps placed over the terrain.
+//!
+//! The world is divided into a grid of [`STRUCTURE_CELL`] cells and each of them rolls for
+//! at most one site from the seed. A site is kept if the land under it is dry, flat enough
+//! and clear of rivers, and no site of the same kind with a higher priority is too close.
+//! Chunks flatten the ground under every footprint reaching into them and spawn the
+//! prefabs of the structures centered inside them.
+
+use bevy::{prelude::*, utils::HashMap};
+use bevy_rapier3d::prelude::*;
+use rand::Rng;
+
+use super::{
+    chunk::ChunkCoord, river::Rivers, scatter::grid_rng, scatter::Placement, Generated, Noise,
+    NoiseConfig,
+};
+
+/// Global cells along each side of a square that gets at most one structure.
+const STRUCTURE_CELL: i32 = 192;
+const STRUCTURE_SALT: u64 = 0x7374_7275;
+/// Chance for a grid cell to roll a site at all.
+const SITE_CHANCE: f64 = 0.6;
+/// Cells between a footprint's edge and where the terrain is untouched again.
+const FOOTPRINT_BLEND: f32 = 8.0;
+/// Lowest normalized height above sea level a structure is built at.
+pub(super) const MIN_SHORE_HEIGHT: f64 = 0.02;
+/// Closest a river's water may come to a footprint, in cells.
+const RIVER_CLEARANCE: f32 = 4.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum StructureKind {
+    Village,
+    Ruins,
+    Camp,
+}
+
+impl StructureKind {
+    pub const ALL: [StructureKind; 3] = [
+        StructureKind::Village,
+        StructureKind::Ruins,
+        StructureKind::Camp,
+    ];
+
+    /// Scene spawned for the structure, relative to the assets folder.
+    pub fn scene(self) -> &'static str {
+        match self {
+            StructureKind::Village => "structures/village.scn.ron",
+            StructureKind::Ruins => "structures/ruins.scn.ron",
+            StructureKind::Camp => "structures/camp.scn.ron",
+        }
+    }
+
+    /// Radius of the flattened ground, in cells.
+    pub fn radius(self) -> f32 {
+        match self {
+            StructureKind::Village => 14.0,
+            StructureKind::Ruins => 9.0,
+            StructureKind::Camp => 5.0,
+        }
+    }
+
+    /// Closest two structures of this kind can be, in cells.
+    pub fn spacing(self) -> f32 {
+        match self {
+            StructureKind::Village => 640.0,
+            StructureKind::Ruins => 320.0,
+            StructureKind::Camp => 240.0,
+        }
+    }
+
+    /// How often a site is this kind compared to the others.
+    fn weight(self) -> u32 {
+        match self {
+            StructureKind::Village => 2,
+            StructureKind::Ruins => 3,
+            StructureKind::Camp => 4,
+        }
+    }
+
+    /// Largest difference in normalized height allowed under the footprint.
+    fn max_relief(self) -> f64 {
+        match self {
+            StructureKind::Village => 0.08,
+            StructureKind::Ruins => 0.2,
+            StructureKind::Camp => 0.12,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            StructureKind::Village => "Village",
+            StructureKind::Ruins => "Ruins",
+            StructureKind::Camp => "Camp",
+        }
+    }
+}
+
+/// Where a grid cell would put its structure, before checking the terrain.
+#[derive(Clone, Copy, Debug)]
+struct Site {
+    kind: StructureKind,
+    center: IVec2,
+    rotation: f32,
+    /// Decides which of two sites too close to each other is kept.
+    priority: u64,
+}
+
+impl Site {
+    fn roll(seed: u32, cell: IVec2) -> Option<Site> {
+        let mut rng = grid_rng(seed, cell, STRUCTURE_SALT);
+        // Always roll everything so the chance doesn't change where sites end up.
+        let exists = rng.gen_bool(SITE_CHANCE);
+        let total: u32 = StructureKind::ALL.iter().map(|kind| kind.weight()).sum();
+        let mut pick = rng.gen_range(0..total);
+        let kind = StructureKind::ALL
+            .into_iter()
+            .find(|kind| match pick.checked_sub(kind.weight()) {
+                Some(rest) => {
+                    pick = rest;
+                    false
+                }
+                None => true,
+            })
+            .unwrap_or(StructureKind::Camp);
+
+        // Sites stay away from the cell's edges so footprints of neighbors never overlap.
+        let margin = StructureKind::ALL
+            .iter()
+            .map(|kind| kind.radius() + FOOTPRINT_BLEND)
+            .fold(0.0, f32::max) as i32;
+        let offset = IVec2::new(
+            rng.gen_range(margin..STRUCTURE_CELL - margin),
+            rng.gen_range(margin..STRUCTURE_CELL - margin),
+        );
+        let rotation = rng.gen_range(0.0..std::f32::consts::TAU);
+        let priority = rng.gen();
+
+        exists.then_some(Site {
+            kind,
+            center: cell * STRUCTURE_CELL + offset,
+            rotation,
+            priority,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Structure {
+    pub kind: StructureKind,
+    /// Global cell at the center of the footprint.
+    pub center: IVec2,
+    /// Normalized height the footprint is flattened to.
+    pub height: f64,
+    /// Rotation around the Y axis, in radians.
+    pub rotation: f32,
+}
+
+impl Structure {
+    /// World position of the center of the footprint on the XZ plane.
+    pub fn world_center(&self) -> Vec2 {
+        Vec2::new(self.center.x as f32, -self.center.y as f32)
+    }
+}
+
+/// Sites checked against the terrain so far, by grid cell.
+#[derive(Resource, Default)]
+pub struct Structures {
+    checked: HashMap<IVec2, Option<f64>>,
+}
+
+impl Structures {
+    pub fn clear(&mut self) {
+        self.checked.clear();
+    }
+
+    /// Height to flatten a site to, or `None` if it can't be built.
+    fn check(
+        &mut self,
+        config: &NoiseConfig,
+        rivers: &mut Rivers,
+        cell: IVec2,
+        site: &Site,
+    ) -> Option<f64> {
+        if let Some(&height) = self.checked.get(&cell) {
+            return height;
+        }
+
+        let radius = site.kind.radius().ceil() as i32;
+        let first = site.center - radius;
+        let size = (radius * 2 + 1) as usize;
+        let map = Noise::from(config)
+            .with_origin(first)
+            .with_size(size, size)
+            .generate_map();
+
+        let center = site.center.as_vec2();
+        let heights: Vec<f64> = (0..size)
+            .flat_map(|y| (0..size).map(move |x| (x, y)))
+            .filter(|&(x, y)| {
+                let cell = first + IVec2::new(x as i32, y as i32);
+                cell.as_vec2().distance(center) <= site.kind.radius()
+            })
+            .map(|(x, y)| map.height_at(x, y))
+            .collect();
+        let lowest = heights.iter().copied().fold(f64::MAX, f64::min);
+        let highest = heights.iter().copied().fold(f64::MIN, f64::max);
+        let average = heights.iter().sum::<f64>() / heights.len() as f64;
+
+        let world_center = Vec2::new(center.x, -center.y);
+        let reach = radius + RIVER_CLEARANCE.ceil() as i32;
+        let dry = rivers
+            .near_cells(config, site.center - reach, site.center + reach)
+            .iter()
+            .all(|river| river.distance(world_center) > site.kind.radius() + RIVER_CLEARANCE);
+
+        let height = (lowest > map.sea_level() + MIN_SHORE_HEIGHT
+            && highest - lowest <= site.kind.max_relief()
+            && average < map.snow_line
+            && dry)
+            .then_some(average);
+        self.checked.insert(cell, height);
+        height
+    }
+
+    /// Structures whose footprints reach into a chunk, checking their sites if needed.
+    pub fn near_chunk(
+        &mut self,
+        config: &NoiseConfig,
+        rivers: &mut Rivers,
+        coord: ChunkCoord,
+    ) -> Vec<Structure> {
+        let origin = coord.origin(config.chunk_size());
+        let size = config.chunk_size() as i32;
+        let reach = StructureKind::ALL
+            .iter()
+            .map(|kind| kind.radius() + FOOTPRINT_BLEND)
+            .fold(0.0, f32::max)
+            .ceil() as i32;
+        // One more cell for the apron chunks are generated with.
+        self.near_cells(config, rivers, origin - 1, origin + size + 1, reach)
+    }
+
+    /// Structures centered within `reach` of the global cells from `first` to `last`,
+    /// checking their sites if needed.
+    pub fn near_cells(
+        &mut self,
+        config: &NoiseConfig,
+        rivers: &mut Rivers,
+        first: IVec2,
+        last: IVec2,
+        reach: i32,
+    ) -> Vec<Structure> {
+        let cell_of = |cell: IVec2| {
+            IVec2::new(
+                cell.x.div_euclid(STRUCTURE_CELL),
+                cell.y.div_euclid(STRUCTURE_CELL),
+            )
+        };
+        let (first_cell, last_cell) = (cell_of(first - reach), cell_of(last + reach));
+
+        let mut structures = Vec::new();
+        for y in first_cell.y..=last_cell.y {
+            for x in first_cell.x..=last_cell.x {
+                let cell = IVec2::new(x, y);
+                let Some(site) = Site::roll(config.seed, cell) else {
+                    continue;
+                };
+                let nearest = site.center.clamp(first, last);
+                if nearest.as_vec2().distance(site.center.as_vec2()) > reach as f32 {
+                    continue;
+                }
+                let Some(height) = self.check(config, rivers, cell, &site) else {
+                    continue;
+                };
+                if self.crowded(config, rivers, cell, &site) {
+                    continue;
+                }
+
+                structures.push(Structure {
+                    kind: site.kind,
+                    center: site.center,
+                    height,
+                    rotation: site.rotation,
+                });
+            }
+        }
+        structures
+    }
+
+    /// Whether a buildable site of the same kind with a higher priority is too close.
+    fn crowded(
+        &mut self,
+        config: &NoiseConfig,
+        rivers: &mut Rivers,
+        cell: IVec2,
+        site: &Site,
+    ) -> bool {
+        let spacing = site.kind.spacing();
+        let cells = (spacing / STRUCTURE_CELL as f32).ceil() as i32;
+        for y in -cells..=cells {
+            for x in -cells..=cells {
+                let other_cell = cell + IVec2::new(x, y);
+                if other_cell == cell {
+                    continue;
+                }
+                let Some(other) = Site::roll(config.seed, other_cell) else {
+                    continue;
+                };
+                if other.kind != site.kind
+                    || other.priority <= site.priority
+                    || other.center.as_vec2().distance(site.center.as_vec2()) >= spacing
+                {
+                    continue;
+                }
+                if self.check(config, rivers, other_cell, &other).is_some() {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+}
+
+/// Flattens the ground under each footprint, blending back into the terrain around it.
+pub fn flatten(map: &mut Noise<Generated>, structures: &[Structure]) {
+    for structure in structures {
+        let radius = structure.kind.radius();
+        let reach = (radius + FOOTPRINT_BLEND).ceil() as i32;
+        let local = structure.center - map.origin;
+
+        for y in (local.y - reach).max(0)..=(local.y + reach).min(map.height as i32 - 1) {
+            for x in (local.x - reach).max(0)..=(local.x + reach).min(map.width as i32 - 1) {
+                let distance = IVec2::new(x, y).as_vec2().distance(local.as_vec2());
+                let t = (1.0 - (distance - radius) / FOOTPRINT_BLEND).clamp(0.0, 1.0);
+                let weight = (t * t * (3.0 - 2.0 * t)) as f64;
+
+                let index = x as usize + y as usize * map.width;
+                let height = map.noise_map[index];
+                map.noise_map[index] = height + (structure.height - height) * weight;
+            }
+        }
+    }
+}
+
+/// Drops scattered objects that would end up inside a structure.
+pub fn clear_footprints(
+    placements: &mut Vec<Placement>,
+    map: &Noise<Generated>,
+    structures: &[Structure],
+) {
+    let origin = map.world_origin().xz();
+    placements.retain(|placement| {
+        let position = origin + placement.position.xz();
+        structures.iter().all(|structure| {
+            position.distance(structure.world_center()) > structure.kind.radius() + 1.0
+        })
+    });
+}
+
+/// A mesh and collider in a structure prefab, built once its scene is spawned.
+#[derive(Component, Reflect, Default, Clone)]
+#[reflect(Component)]
+pub struct StructurePiece {
+    pub shape: PieceShape,
+    /// Full size of the piece. Cylinders use `x` as their diameter.
+    pub size: Vec3,
+    pub color: Color,
+}
+
+#[derive(Reflect, Default, Clone, Copy, Debug)]
+pub enum PieceShape {
+    #[default]
+    Cuboid,
+    Cylinder,
+}
+
+/// Prefab scenes of every structure kind.
+#[derive(Resource)]
+pub struct StructureAssets {
+    scenes: HashMap<StructureKind, Handle<DynamicScene>>,
+}
+
+impl FromWorld for StructureAssets {
+    fn from_world(world: &mut World) -> Self {
+        let asset_server = world.resource::<AssetServer>();
+        Self {
+            scenes: StructureKind::ALL
+                .into_iter()
+                .map(|kind| (kind, asset_server.load(kind.scene())))
+                .collect(),
+        }
+    }
+}
+
+/// Spawns the prefabs of the structures centered in a chunk as children of it.
+pub(super) fn spawn_structures(
+    parent: &mut ChildBuilder,
+    map: &Noise<Generated>,
+    structures: &[Structure],
+    assets: &StructureAssets,
+) {
+    let origin = map.world_origin();
+    let size = (map.width - 1) as i32;
+    for structure in structures {
+        // Only the chunk owning the center cell spawns it, like the chunk's own cells.
+        let local = structure.center - map.origin;
+        if local.cmplt(IVec2::ZERO).any() || local.cmpge(IVec2::splat(size)).any() {
+            continue;
+        }
+
+        let height = structure.height as f32 * map.height_multiplier;
+        let center = structure.world_center();
+        parent.spawn((
+            DynamicSceneBundle {
+                scene: assets.scenes[&structure.kind].clone(),
+                transform: Transform::from_translation(
+                    Vec3::new(center.x, height, center.y) - origin,
+                )
+                .with_rotation(Quat::from_rotation_y(structure.rotation)),
+                ..Default::default()
+            },
+            Name::new(structure.kind.name()),
+        ));
+    }
+}
+
+pub(super) fn build_structure_pieces(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    piece_query: Query<(Entity, &StructurePiece), Added<StructurePiece>>,
+) {
+    for (entity, piece) in piece_query.iter() {
+        let (mesh, collider) = match piece.shape {
+            PieceShape::Cuboid => (
+                shape::Box::new(piece.size.x, piece.size.y, piece.size.z).into(),
+                Collider::cuboid(piece.size.x / 2.0, piece.size.y / 2.0, piece.size.z / 2.0),
+            ),
+            PieceShape::Cylinder => (
+                shape::Cylinder {
+                    radius: piece.size.x / 2.0,
+                    height: piece.size.y,
+                    ..Default::default()
+                }
+                .into(),
+                Collider::cylinder(piece.size.y / 2.0, piece.size.x / 2.0),
+            ),
+        };
+
+        // Scene files only store the transform, the rest of the render components are
+        // added here along with the mesh.
+        commands.entity(entity).insert((
+            meshes.add(mesh),
+            materials.add(piece.color.into()),
+            GlobalTransform::default(),
+            VisibilityBundle::default(),
+            collider,
+        ));
+    }
+}
@@ -0,0 +1,280 @@
+use bevy::{
+    prelude::*,
+    render::{mesh::Indices, render_resource::PrimitiveTopology},
+    utils::HashSet,
+};
+
+use super::{
+    chunk::{ChunkCoord, ChunkLoaded, ChunkModified, Terrain},
+    diff::ChunkDiffs,
+    harvestables::{self, HarvestableAssets},
+    scatter::{Placement, ScatterKind},
+    NoiseConfig,
+};
+
+/// How far, in chunks around the camera, each level of detail is drawn.
+///
+/// Harvestable nodes are only spawned as entities close to the camera. Further away a
+/// chunk draws all of them as one merged mesh of camera-independent crossed quads, and
+/// past `impostor_distance` nothing is drawn at all.
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+pub struct VegetationSettings {
+    pub node_distance: u32,
+    pub grass_distance: u32,
+    pub impostor_distance: u32,
+}
+
+impl Default for VegetationSettings {
+    fn default() -> Self {
+        Self {
+            node_distance: 1,
+            grass_distance: 1,
+            impostor_distance: 3,
+        }
+    }
+}
+
+/// Merged vegetation of a chunk, children of the chunk entity.
+#[derive(Component)]
+pub struct ChunkVegetation {
+    grass: Entity,
+    impostors: Entity,
+    /// Parent of the harvestable nodes while the chunk is close enough to have them.
+    nodes: Option<Entity>,
+}
+
+/// Double sided material that takes its color from the merged meshes.
+#[derive(Resource)]
+pub struct VegetationMaterial(pub Handle<StandardMaterial>);
+
+impl FromWorld for VegetationMaterial {
+    fn from_world(world: &mut World) -> Self {
+        let mut materials = world.resource_mut::<Assets<StandardMaterial>>();
+        Self(materials.add(StandardMaterial {
+            base_color: Color::WHITE,
+            perceptual_roughness: 0.9,
+            cull_mode: None,
+            double_sided: true,
+            ..Default::default()
+        }))
+    }
+}
+
+/// Vertex buffers of a mesh built from many small pieces.
+#[derive(Default)]
+struct MergedMesh {
+    positions: Vec<Vec3>,
+    normals: Vec<Vec3>,
+    colors: Vec<[f32; 4]>,
+    indices: Vec<u32>,
+}
+
+impl MergedMesh {
+    /// Adds two vertical quads crossing at `base`, fading from `bottom` to `top` color.
+    fn add_crossed_quads(&mut self, placement: &Placement, size: Vec2, bottom: Color, top: Color) {
+        let rotation = Quat::from_rotation_y(placement.rotation);
+        let size = size * placement.scale;
+
+        for axis in [Vec3::X, Vec3::Z] {
+            let half = rotation * axis * size.x / 2.0;
+            let up = Vec3::Y * size.y;
+            let first = self.positions.len() as u32;
+
+            self.positions.extend([
+                placement.position - half,
+                placement.position + half,
+                placement.position + half + up,
+                placement.position - half + up,
+            ]);
+            // Pointing every normal up keeps both sides evenly lit like the terrain below.
+            self.normals.extend([Vec3::Y; 4]);
+            self.colors.extend([
+                bottom.as_linear_rgba_f32(),
+                bottom.as_linear_rgba_f32(),
+                top.as_linear_rgba_f32(),
+                top.as_linear_rgba_f32(),
+            ]);
+            self.indices
+                .extend([first, first + 1, first + 2, first, first + 2, first + 3]);
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    fn into_mesh(self) -> Mesh {
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList)
+            .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, self.positions)
+            .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, self.normals)
+            .with_inserted_attribute(Mesh::ATTRIBUTE_COLOR, self.colors);
+        mesh.set_indices(Some(Indices::U32(self.indices)));
+        mesh
+    }
+}
+
+/// Size and colors of the crossed quads drawn for a kind in place of the real object.
+fn impostor_of(kind: ScatterKind) -> (Vec2, Color, Color) {
+    match kind {
+        ScatterKind::Tree => (
+            Vec2::new(2.0, 3.5),
+            Color::rgb(0.4, 0.25, 0.1),
+            Color::rgb(0.1, 0.4, 0.1),
+        ),
+        ScatterKind::Bush => (Vec2::new(0.8, 0.7), Color::DARK_GREEN, Color::DARK_GREEN),
+        ScatterKind::Rock => (Vec2::new(1.0, 0.5), Color::GRAY, Color::GRAY),
+        ScatterKind::Grass => (
+            Vec2::new(0.4, 0.5),
+            Color::rgb(0.2, 0.45, 0.1),
+            Color::rgb(0.45, 0.7, 0.2),
+        ),
+    }
+}
+
+fn merge(placements: &[Placement], filter: impl Fn(ScatterKind) -> bool) -> MergedMesh {
+    let mut merged = MergedMesh::default();
+    for placement in placements.iter().filter(|placement| filter(placement.kind)) {
+        let (size, bottom, top) = impostor_of(placement.kind);
+        merged.add_crossed_quads(placement, size, bottom, top);
+    }
+    merged
+}
+
+/// Builds the merged grass and impostor meshes of loaded chunks, and rebuilds them when
+/// a chunk's terrain is edited.
+#[allow(clippy::too_many_arguments)]
+pub(super) fn spawn_vegetation(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut loaded_events: EventReader<ChunkLoaded>,
+    mut modified_events: EventReader<ChunkModified>,
+    terrain: Res<Terrain>,
+    material: Res<VegetationMaterial>,
+    vegetation_query: Query<&ChunkVegetation>,
+) {
+    let coords: HashSet<ChunkCoord> = loaded_events
+        .read()
+        .map(|ChunkLoaded(coord)| *coord)
+        .chain(modified_events.read().map(|ChunkModified(coord)| *coord))
+        .collect();
+
+    for coord in coords {
+        let Some(chunk) = terrain.get(coord) else {
+            continue;
+        };
+
+        if let Ok(old) = vegetation_query.get(chunk.entity) {
+            for entity in [Some(old.grass), Some(old.impostors), old.nodes]
+                .into_iter()
+                .flatten()
+            {
+                commands.entity(entity).despawn_recursive();
+            }
+        }
+
+        let mut spawn_merged = |merged: MergedMesh, name: &str| {
+            let mesh = match merged.is_empty() {
+                true => Handle::default(),
+                false => meshes.add(merged.into_mesh()),
+            };
+            commands
+                .spawn((
+                    PbrBundle {
+                        mesh,
+                        material: material.0.clone(),
+                        visibility: Visibility::Hidden,
+                        ..Default::default()
+                    },
+                    Name::new(name.to_string()),
+                ))
+                .id()
+        };
+
+        let grass = spawn_merged(
+            merge(&chunk.scatter, |kind| kind == ScatterKind::Grass),
+            "Grass",
+        );
+        let impostors = spawn_merged(
+            merge(&chunk.scatter, |kind| kind != ScatterKind::Grass),
+            "Impostors",
+        );
+
+        commands
+            .entity(chunk.entity)
+            .push_children(&[grass, impostors])
+            .insert(ChunkVegetation {
+                grass,
+                impostors,
+                nodes: None,
+            });
+    }
+}
+
+/// Picks what each chunk draws from its distance to the camera.
+#[allow(clippy::too_many_arguments)]
+pub(super) fn update_vegetation_lod(
+    mut commands: Commands,
+    config: Res<NoiseConfig>,
+    settings: Res<VegetationSettings>,
+    terrain: Res<Terrain>,
+    diffs: Res<ChunkDiffs>,
+    node_assets: Res<HarvestableAssets>,
+    camera_query: Query<&GlobalTransform, With<Camera3d>>,
+    mut chunk_query: Query<(Entity, &ChunkCoord, &mut ChunkVegetation)>,
+    mut visibility_query: Query<&mut Visibility>,
+) {
+    let Ok(camera) = camera_query.get_single() else {
+        return;
+    };
+    let center = ChunkCoord::from_world(camera.translation().xz(), config.chunk_size());
+
+    for (entity, coord, mut vegetation) in chunk_query.iter_mut() {
+        let distance = coord.distance(center) as u32;
+        let near = distance <= settings.node_distance;
+
+        let mut set_visible = |target: Entity, visible: bool| {
+            if let Ok(mut visibility) = visibility_query.get_mut(target) {
+                let wanted = match visible {
+                    true => Visibility::Inherited,
+                    false => Visibility::Hidden,
+                };
+                if *visibility != wanted {
+                    *visibility = wanted;
+                }
+            }
+        };
+        set_visible(vegetation.grass, distance <= settings.grass_distance);
+        set_visible(
+            vegetation.impostors,
+            !near && distance <= settings.impostor_distance,
+        );
+
+        match (near, vegetation.nodes) {
+            (true, None) => {
+                let Some(chunk) = terrain.get(*coord) else {
+                    continue;
+                };
+                let nodes = commands
+                    .spawn((SpatialBundle::default(), Name::new("Nodes")))
+                    .with_children(|parent| {
+                        harvestables::spawn_nodes(
+                            parent,
+                            *coord,
+                            &chunk.scatter,
+                            diffs.get(*coord),
+                            &node_assets,
+                        )
+                    })
+                    .id();
+                commands.entity(entity).add_child(nodes);
+                vegetation.nodes = Some(nodes);
+            }
+            (false, Some(nodes)) => {
+                commands.entity(nodes).despawn_recursive();
+                vegetation.nodes = None;
+            }
+            _ => {}
+        }
+    }
+}
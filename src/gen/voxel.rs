@@ -0,0 +1,238 @@
+//! Surface nets over a 3D density field, used by [`DrawMode::Voxel`](super::DrawMode).
+//!
+//! The density is the heightmap surface with caves carved out of it by two layers of 3D
+//! noise, so the same chunk maps still decide where the ground is and what grows on it.
+
+use bevy::math::{IVec3, Vec2, Vec3};
+use noise::{NoiseFn, Perlin};
+
+use super::{Generated, MeshData, Noise};
+
+/// How far below sea level the voxel terrain reaches, in world units.
+const CAVE_DEPTH: f32 = 16.0;
+/// World units per cycle of the cave noise.
+const CAVE_SCALE: f64 = 24.0;
+/// How close to zero both cave noises have to be for a point to be hollow.
+const CAVE_THRESHOLD: f64 = 0.08;
+/// Scales the cave noise into roughly the same units as the surface distance.
+const CAVE_SHARPNESS: f64 = 40.0;
+/// Color of surfaces deep enough below the heightmap surface to be inside a cave.
+const CAVE_COLOR: [f32; 4] = [0.3, 0.28, 0.25, 1.0];
+
+/// Samples the density of a chunk. Positive is solid, negative is air.
+struct DensityField<'a> {
+    /// Heightmap one cell larger than the chunk on every side, in world units.
+    surface: Vec<f32>,
+    surface_width: usize,
+    caves: [Perlin; 2],
+    map: &'a Noise<Generated>,
+}
+
+impl<'a> DensityField<'a> {
+    fn new(map: &'a Noise<Generated>) -> Self {
+        // The padding comes from the map's apron, which neighboring chunks agree on.
+        let surface_width = map.width + 2;
+        let surface: Vec<f32> = (-1..=map.height as i32)
+            .flat_map(|y| (-1..=map.width as i32).map(move |x| (x, y)))
+            .map(|(x, y)| map.height_around(x, y) as f32 * map.height_multiplier)
+            .collect();
+
+        Self {
+            surface,
+            surface_width,
+            caves: [
+                Perlin::new(map.seed.wrapping_add(2)),
+                Perlin::new(map.seed.wrapping_add(3)),
+            ],
+            map,
+        }
+    }
+
+    /// Surface height at a map position, which may be one cell outside the map.
+    fn surface_at(&self, x: f32, y: f32) -> f32 {
+        let max = (self.surface_width - 1) as f32;
+        let x = (x + 1.0).clamp(0.0, max);
+        let y = (y + 1.0).clamp(0.0, max);
+        let (x0, y0) = (x.floor() as usize, y.floor() as usize);
+        let (x1, y1) = (
+            (x0 + 1).min(self.surface_width - 1),
+            (y0 + 1).min(self.surface_width - 1),
+        );
+        let (tx, ty) = (x - x0 as f32, y - y0 as f32);
+
+        let at = |x: usize, y: usize| self.surface[x + y * self.surface_width];
+        let top = at(x0, y0) + (at(x1, y0) - at(x0, y0)) * tx;
+        let bottom = at(x0, y1) + (at(x1, y1) - at(x0, y1)) * tx;
+        top + (bottom - top) * ty
+    }
+
+    /// Density at a point relative to the chunk, `x` and `z` in map cells.
+    fn density(&self, point: Vec3) -> f32 {
+        let surface = self.surface_at(point.x, -point.z) - point.y;
+
+        let global = Vec3::new(
+            self.map.origin.x as f32 + point.x,
+            point.y,
+            self.map.origin.y as f32 - point.z,
+        )
+        .as_dvec3()
+            / CAVE_SCALE;
+        let cave = self
+            .caves
+            .iter()
+            .map(|perlin| perlin.get(global.to_array()).abs())
+            .fold(0.0, f64::max);
+        let cave = ((cave - CAVE_THRESHOLD) * CAVE_SHARPNESS) as f32;
+
+        // Everything below the cave layer is solid so the mesh stays closed.
+        let floor = -CAVE_DEPTH - point.y;
+        surface.min(cave).max(floor)
+    }
+
+    /// Points from solid towards air.
+    fn normal(&self, point: Vec3) -> Vec3 {
+        let e = 0.5;
+        let gradient = Vec3::new(
+            self.density(point + Vec3::X * e) - self.density(point - Vec3::X * e),
+            self.density(point + Vec3::Y * e) - self.density(point - Vec3::Y * e),
+            self.density(point + Vec3::Z * e) - self.density(point - Vec3::Z * e),
+        );
+        (-gradient).normalize_or_zero()
+    }
+}
+
+impl Noise<Generated> {
+    /// Meshes the chunk with surface nets instead of straight from the heightmap.
+    ///
+    /// The sample grid reaches one cell past the negative sides of the chunk so faces
+    /// on its borders can be built, but only edges starting inside the chunk produce
+    /// faces, so neighboring chunks never build the same face twice.
+    pub fn generate_voxel_mesh(&self) -> MeshData {
+        let field = DensityField::new(self);
+        let size = (self.width - 1) as i32;
+        let min = IVec3::new(-1, (-CAVE_DEPTH).floor() as i32 - 1, -1);
+        let max = IVec3::new(size, self.height_multiplier.ceil() as i32 + 1, size);
+        let dims = (max - min + IVec3::ONE).as_uvec3();
+
+        // Samples are laid out with `x` and `z` in map cells, `z` growing away from
+        // the chunk origin to match the heightmap mesh.
+        let position =
+            |sample: IVec3| Vec3::new(sample.x as f32, sample.y as f32, -sample.z as f32);
+        let index = |sample: IVec3| {
+            let local = (sample - min).as_uvec3();
+            (local.x + local.y * dims.x + local.z * dims.x * dims.y) as usize
+        };
+
+        let mut densities = vec![0.0; (dims.x * dims.y * dims.z) as usize];
+        for z in min.z..=max.z {
+            for y in min.y..=max.y {
+                for x in min.x..=max.x {
+                    let sample = IVec3::new(x, y, z);
+                    densities[index(sample)] = field.density(position(sample));
+                }
+            }
+        }
+
+        // One vertex per cell the surface passes through, at the average of the
+        // points where it crosses the cell's edges.
+        let corners: Vec<IVec3> = (0..8)
+            .map(|i| IVec3::new(i & 1, (i >> 1) & 1, (i >> 2) & 1))
+            .collect();
+        let edges: Vec<(usize, usize)> = (0..8)
+            .flat_map(|a| [1, 2, 4].into_iter().map(move |bit| (a, a | bit)))
+            .filter(|&(a, b)| a != b)
+            .collect();
+
+        let mut mesh_data = MeshData {
+            vertices: Vec::new(),
+            triangles: Vec::new(),
+            uvs: Vec::new(),
+            colors: Vec::new(),
+            normals: Vec::new(),
+            tangents: Vec::new(),
+            triangle_index: 0,
+        };
+        let colors = self.colorize_map();
+        let mut cell_vertex = vec![None; densities.len()];
+
+        for z in min.z..max.z {
+            for y in min.y..max.y {
+                for x in min.x..max.x {
+                    let cell = IVec3::new(x, y, z);
+                    let values: Vec<f32> = corners
+                        .iter()
+                        .map(|&corner| densities[index(cell + corner)])
+                        .collect();
+
+                    let mut sum = Vec3::ZERO;
+                    let mut crossings = 0;
+                    for &(a, b) in &edges {
+                        if (values[a] > 0.0) != (values[b] > 0.0) {
+                            let t = values[a] / (values[a] - values[b]);
+                            sum += position(cell + corners[a]).lerp(position(cell + corners[b]), t);
+                            crossings += 1;
+                        }
+                    }
+                    if crossings == 0 {
+                        continue;
+                    }
+
+                    let vertex = sum / crossings as f32;
+                    let (map_x, map_y) = (
+                        (vertex.x.round().max(0.0) as usize).min(self.width - 1),
+                        ((-vertex.z).round().max(0.0) as usize).min(self.height - 1),
+                    );
+                    let color = match vertex.y < field.surface_at(vertex.x, -vertex.z) - 1.0 {
+                        true => CAVE_COLOR,
+                        false => colors[map_x + map_y * self.width],
+                    };
+
+                    cell_vertex[index(cell)] = Some(mesh_data.vertices.len());
+                    mesh_data.vertices.push(vertex);
+                    mesh_data.normals.push(field.normal(vertex));
+                    mesh_data.colors.push(color);
+                    mesh_data
+                        .uvs
+                        .push(Vec2::new(vertex.x / size as f32, -vertex.z / size as f32));
+                }
+            }
+        }
+
+        // Every edge with a sign change gets a quad joining the four cells around it.
+        // Starting at zero leaves the padding cells to the neighboring chunk's edges.
+        for z in 0..max.z {
+            for y in (min.y + 1)..max.y {
+                for x in 0..max.x {
+                    let sample = IVec3::new(x, y, z);
+                    let solid = densities[index(sample)] > 0.0;
+
+                    for axis in 0..3 {
+                        let u = IVec3::AXES[(axis + 1) % 3];
+                        let v = IVec3::AXES[(axis + 2) % 3];
+                        let next = sample + IVec3::AXES[axis];
+                        if next.cmpgt(max).any() || solid == (densities[index(next)] > 0.0) {
+                            continue;
+                        }
+
+                        let quad = [sample - u - v, sample - v, sample, sample - u]
+                            .map(|cell| cell_vertex[index(cell)]);
+                        let [Some(a), Some(b), Some(c), Some(d)] = quad else {
+                            continue;
+                        };
+
+                        // Sample `z` grows towards world -Z, which mirrors the grid, so
+                        // `a, b, c` faces back along the edge in world space.
+                        let triangles = match solid {
+                            true => [a, c, b, a, d, c],
+                            false => [a, b, c, a, c, d],
+                        };
+                        mesh_data.triangles.extend(triangles);
+                    }
+                }
+            }
+        }
+
+        mesh_data.triangle_index = mesh_data.triangles.len();
+        mesh_data
+    }
+}
@@ -1,21 +1,248 @@
-pub trait Item {
-    fn ways(&self) -> Box<[&'static dyn Item]>;
+use std::fmt;
 
-    fn source(&self) -> Box<[&'static dyn Item]>;
+use bevy::{ecs::system::Resource, utils::HashMap};
+use serde::{Deserialize, Serialize};
+
+use super::item::{ItemContainer, ItemId, ItemRegistry, ItemStack, RegistryError};
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct RecipeId(pub String);
+
+impl From<&str> for RecipeId {
+    fn from(value: &str) -> Self {
+        Self(value.to_owned())
+    }
+}
+
+impl fmt::Display for RecipeId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// A place that has to be nearby for some recipes, like a workbench or a furnace.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct StationId(pub String);
+
+impl From<&str> for StationId {
+    fn from(value: &str) -> Self {
+        Self(value.to_owned())
+    }
+}
+
+impl fmt::Display for StationId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Recipe {
+    pub id: RecipeId,
+    pub inputs: Vec<ItemStack>,
+    pub outputs: Vec<ItemStack>,
+    #[serde(default)]
+    pub station: Option<StationId>,
+    /// Seconds it takes to craft.
+    #[serde(default = "default_craft_time")]
+    pub craft_time: f32,
+}
+
+fn default_craft_time() -> f32 {
+    1.0
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CraftError {
+    UnknownRecipe(RecipeId),
+    MissingStation(StationId),
+    /// Inputs that are missing, with the amount still needed.
+    MissingInputs(Vec<ItemStack>),
+    /// The outputs would not fit into the container.
+    NoSpace,
+}
+
+impl fmt::Display for CraftError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CraftError::UnknownRecipe(id) => write!(f, "unknown recipe `{id}`"),
+            CraftError::MissingStation(station) => write!(f, "requires a `{station}` nearby"),
+            CraftError::MissingInputs(missing) => {
+                write!(f, "missing ")?;
+                for (i, stack) in missing.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{} x{}", stack.item, stack.count)?;
+                }
+                Ok(())
+            }
+            CraftError::NoSpace => write!(f, "not enough space for the result"),
+        }
+    }
+}
+
+impl std::error::Error for CraftError {}
+
+/// Every recipe that exists in the game, by id.
+#[derive(Resource, Default, Debug)]
+pub struct RecipeRegistry {
+    recipes: HashMap<RecipeId, Recipe>,
 }
 
-// pub struct Rock;
+impl RecipeRegistry {
+    /// Adds a recipe after checking that its id is new and all of its items are known.
+    pub fn register(&mut self, items: &ItemRegistry, recipe: Recipe) -> Result<(), RegistryError> {
+        if self.recipes.contains_key(&recipe.id) {
+            return Err(RegistryError::DuplicateRecipe(recipe.id));
+        }
+        let unknown = recipe
+            .inputs
+            .iter()
+            .chain(recipe.outputs.iter())
+            .find(|stack| !items.contains(&stack.item));
+        if let Some(stack) = unknown {
+            return Err(RegistryError::UnknownItem(stack.item.clone()));
+        }
 
-// impl Item for Rock {
-//     fn ways(&self) -> Box<[&'static dyn Item]> {
-//         Box::new([&Stick])
-//     }
-// }
+        self.recipes.insert(recipe.id.clone(), recipe);
+        Ok(())
+    }
 
-// pub struct Stick;
+    pub fn get(&self, id: &RecipeId) -> Option<&Recipe> {
+        self.recipes.get(id)
+    }
 
-// impl Item for Stick {
-//     fn ways(&self) -> Box<[&'static dyn Item]> {
-//         Box::new([])
-//     }
-// }
+    pub fn iter(&self) -> impl Iterator<Item = &Recipe> {
+        self.recipes.values()
+    }
+
+    /// Recipes that produce `item`.
+    pub fn producing<'a>(&'a self, item: &'a ItemId) -> impl Iterator<Item = &'a Recipe> {
+        self.iter()
+            .filter(move |recipe| recipe.outputs.iter().any(|stack| &stack.item == item))
+    }
+
+    /// Recipes that consume `item`.
+    pub fn using<'a>(&'a self, item: &'a ItemId) -> impl Iterator<Item = &'a Recipe> {
+        self.iter()
+            .filter(move |recipe| recipe.inputs.iter().any(|stack| &stack.item == item))
+    }
+}
+
+/// Checks whether `recipe` can be crafted from `container` with the given stations around.
+pub fn can_craft(
+    items: &ItemRegistry,
+    recipe: &Recipe,
+    container: &(impl ItemContainer + Clone),
+    stations: &[StationId],
+) -> Result<(), CraftError> {
+    if let Some(station) = &recipe.station {
+        if !stations.contains(station) {
+            return Err(CraftError::MissingStation(station.clone()));
+        }
+    }
+
+    let missing: Vec<ItemStack> = recipe
+        .inputs
+        .iter()
+        .filter_map(|stack| {
+            let available = container.count(&stack.item);
+            (available < stack.count).then(|| ItemStack {
+                item: stack.item.clone(),
+                count: stack.count - available,
+            })
+        })
+        .collect();
+    if !missing.is_empty() {
+        return Err(CraftError::MissingInputs(missing));
+    }
+
+    // The inputs are consumed first, which can free up the space the outputs need.
+    let mut remaining = container.clone();
+    for stack in &recipe.inputs {
+        remaining.remove(&stack.item, stack.count);
+    }
+    if !remaining.can_insert(items, &recipe.outputs) {
+        return Err(CraftError::NoSpace);
+    }
+
+    Ok(())
+}
+
+/// Crafts `recipe` once, consuming its inputs from `container` and putting the outputs back.
+///
+/// Nothing is changed if the recipe can't be crafted.
+pub fn craft(
+    items: &ItemRegistry,
+    recipe: &Recipe,
+    container: &mut (impl ItemContainer + Clone),
+    stations: &[StationId],
+) -> Result<(), CraftError> {
+    can_craft(items, recipe, container, stations)?;
+
+    for stack in &recipe.inputs {
+        container.remove(&stack.item, stack.count);
+    }
+    for stack in &recipe.outputs {
+        container.insert(items, stack.clone());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logic::inventory::Inventory;
+
+    fn planks() -> Recipe {
+        Recipe {
+            id: "planks".into(),
+            inputs: vec![ItemStack::new("log", 1)],
+            outputs: vec![ItemStack::new("plank", 4)],
+            station: None,
+            craft_time: 1.0,
+        }
+    }
+
+    #[test]
+    fn recipe_ids_are_registered_once() {
+        let items = ItemRegistry::from_ids(&[("log", 64), ("plank", 64)]);
+        let mut recipes = RecipeRegistry::default();
+
+        assert_eq!(recipes.register(&items, planks()), Ok(()));
+        let mut again = planks();
+        again.outputs = vec![ItemStack::new("plank", 2)];
+        assert_eq!(
+            recipes.register(&items, again),
+            Err(RegistryError::DuplicateRecipe("planks".into()))
+        );
+        assert_eq!(
+            recipes.get(&"planks".into()).unwrap().outputs,
+            vec![ItemStack::new("plank", 4)]
+        );
+    }
+
+    #[test]
+    fn consumed_inputs_make_room_for_the_outputs() {
+        let items = ItemRegistry::from_ids(&[("log", 64), ("plank", 64)]);
+        let recipe = planks();
+
+        // The only slot holds the log the planks are made from.
+        let mut inventory = Inventory::new(1);
+        inventory.insert(&items, ItemStack::new("log", 1));
+        assert_eq!(craft(&items, &recipe, &mut inventory, &[]), Ok(()));
+        assert_eq!(inventory.get(0), Some(&ItemStack::new("plank", 4)));
+
+        // With two logs one stays behind and the planks have nowhere to go.
+        let mut inventory = Inventory::new(1);
+        inventory.insert(&items, ItemStack::new("log", 2));
+        assert_eq!(
+            can_craft(&items, &recipe, &inventory, &[]),
+            Err(CraftError::NoSpace)
+        );
+    }
+}
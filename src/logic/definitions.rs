@@ -0,0 +1,466 @@
+use std::{fmt, path::PathBuf};
+
+use bevy::{
+    asset::{
+        io::Reader, Asset, AssetEvent, AssetLoader, AssetServer, Assets, AsyncReadExt, Handle,
+        LoadContext, LoadedFolder,
+    },
+    ecs::{
+        event::EventReader,
+        system::{Commands, Res, ResMut, Resource},
+    },
+    log::{error, info},
+    reflect::TypePath,
+    utils::{BoxedFuture, HashMap, HashSet},
+};
+use serde::Deserialize;
+
+use super::{
+    crafting::{Recipe, RecipeId, RecipeRegistry},
+    item::{ItemDef, ItemId, ItemRegistry},
+};
+
+/// Folder under `assets` that every `*.items.ron` file is loaded from.
+pub const DEFINITIONS_FOLDER: &str = "items";
+
+/// Tag marking items that are gathered from the world rather than crafted.
+pub const RAW_TAG: &str = "raw";
+
+/// Items and recipes declared in a single asset file.
+#[derive(Asset, TypePath, Debug, Deserialize)]
+pub struct ItemDefinitions {
+    #[serde(skip)]
+    pub path: PathBuf,
+    #[serde(default)]
+    pub items: Vec<ItemDef>,
+    #[serde(default)]
+    pub recipes: Vec<Recipe>,
+}
+
+#[derive(Debug)]
+pub enum DefinitionsLoaderError {
+    Io(std::io::Error),
+    Ron(ron::error::SpannedError),
+}
+
+impl fmt::Display for DefinitionsLoaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DefinitionsLoaderError::Io(err) => write!(f, "could not read definitions: {err}"),
+            DefinitionsLoaderError::Ron(err) => write!(f, "could not parse definitions: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for DefinitionsLoaderError {}
+
+#[derive(Default)]
+pub struct ItemDefinitionsLoader;
+
+impl AssetLoader for ItemDefinitionsLoader {
+    type Asset = ItemDefinitions;
+    type Settings = ();
+    type Error = DefinitionsLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a Self::Settings,
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader
+                .read_to_end(&mut bytes)
+                .await
+                .map_err(DefinitionsLoaderError::Io)?;
+            let mut definitions: ItemDefinitions =
+                ron::de::from_bytes(&bytes).map_err(DefinitionsLoaderError::Ron)?;
+            definitions.path = load_context.path().to_path_buf();
+            Ok(definitions)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["items.ron"]
+    }
+}
+
+/// A problem found while checking item definitions, with the file it came from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DefinitionError {
+    DuplicateItem {
+        item: ItemId,
+        path: PathBuf,
+    },
+    DuplicateRecipe {
+        recipe: RecipeId,
+        path: PathBuf,
+    },
+    InvalidStackSize {
+        item: ItemId,
+        path: PathBuf,
+    },
+    UnknownItem {
+        recipe: RecipeId,
+        item: ItemId,
+        path: PathBuf,
+    },
+    NoOutputs {
+        recipe: RecipeId,
+        path: PathBuf,
+    },
+    /// The same item is listed more than once in a recipe's outputs.
+    DuplicateOutput {
+        recipe: RecipeId,
+        item: ItemId,
+        path: PathBuf,
+    },
+    /// The item is not crafted by any recipe and isn't tagged as raw.
+    MissingSource {
+        item: ItemId,
+        path: PathBuf,
+    },
+}
+
+impl fmt::Display for DefinitionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DefinitionError::DuplicateItem { item, path } => {
+                write!(
+                    f,
+                    "{}: item `{item}` is defined more than once",
+                    path.display()
+                )
+            }
+            DefinitionError::DuplicateRecipe { recipe, path } => write!(
+                f,
+                "{}: recipe `{recipe}` is defined more than once",
+                path.display()
+            ),
+            DefinitionError::InvalidStackSize { item, path } => {
+                write!(f, "{}: item `{item}` has a max stack of 0", path.display())
+            }
+            DefinitionError::UnknownItem { recipe, item, path } => write!(
+                f,
+                "{}: recipe `{recipe}` uses unknown item `{item}`",
+                path.display()
+            ),
+            DefinitionError::NoOutputs { recipe, path } => {
+                write!(f, "{}: recipe `{recipe}` has no outputs", path.display())
+            }
+            DefinitionError::DuplicateOutput { recipe, item, path } => write!(
+                f,
+                "{}: recipe `{recipe}` lists output `{item}` more than once",
+                path.display()
+            ),
+            DefinitionError::MissingSource { item, path } => write!(
+                f,
+                "{}: item `{item}` is not crafted by any recipe and is not tagged `{RAW_TAG}`",
+                path.display()
+            ),
+        }
+    }
+}
+
+/// Checks a set of definition files together and builds registries from them.
+///
+/// All problems are collected instead of stopping at the first one so designers can
+/// fix a whole file in one go.
+pub fn build_registries<'a>(
+    files: impl IntoIterator<Item = &'a ItemDefinitions>,
+) -> Result<(ItemRegistry, RecipeRegistry), Vec<DefinitionError>> {
+    let files: Vec<&ItemDefinitions> = files.into_iter().collect();
+    let mut errors = Vec::new();
+
+    let mut items = ItemRegistry::default();
+    let mut item_paths: HashMap<ItemId, PathBuf> = HashMap::new();
+    for file in &files {
+        for item in &file.items {
+            if item.max_stack == 0 {
+                errors.push(DefinitionError::InvalidStackSize {
+                    item: item.id.clone(),
+                    path: file.path.clone(),
+                });
+            }
+            if items.register(item.clone()).is_err() {
+                errors.push(DefinitionError::DuplicateItem {
+                    item: item.id.clone(),
+                    path: file.path.clone(),
+                });
+            } else {
+                item_paths.insert(item.id.clone(), file.path.clone());
+            }
+        }
+    }
+
+    let mut recipes = RecipeRegistry::default();
+    let mut recipe_ids = HashSet::new();
+    let mut produced = HashSet::new();
+    for file in &files {
+        for recipe in &file.recipes {
+            let path = || file.path.clone();
+            let mut valid = true;
+
+            if !recipe_ids.insert(recipe.id.clone()) {
+                errors.push(DefinitionError::DuplicateRecipe {
+                    recipe: recipe.id.clone(),
+                    path: path(),
+                });
+                valid = false;
+            }
+            if recipe.outputs.is_empty() {
+                errors.push(DefinitionError::NoOutputs {
+                    recipe: recipe.id.clone(),
+                    path: path(),
+                });
+                valid = false;
+            }
+
+            let mut outputs = HashSet::new();
+            for output in &recipe.outputs {
+                if !outputs.insert(&output.item) {
+                    errors.push(DefinitionError::DuplicateOutput {
+                        recipe: recipe.id.clone(),
+                        item: output.item.clone(),
+                        path: path(),
+                    });
+                    valid = false;
+                }
+                produced.insert(output.item.clone());
+            }
+
+            for stack in recipe.inputs.iter().chain(recipe.outputs.iter()) {
+                if !items.contains(&stack.item) {
+                    errors.push(DefinitionError::UnknownItem {
+                        recipe: recipe.id.clone(),
+                        item: stack.item.clone(),
+                        path: path(),
+                    });
+                    valid = false;
+                }
+            }
+
+            if valid {
+                // The id and every item were checked above, so registering can't fail.
+                let _ = recipes.register(&items, recipe.clone());
+            }
+        }
+    }
+
+    let mut unsourced: Vec<&ItemDef> = items
+        .iter()
+        .filter(|item| !produced.contains(&item.id) && !item.has_tag(RAW_TAG))
+        .collect();
+    unsourced.sort_by(|a, b| a.id.cmp(&b.id));
+    for item in unsourced {
+        errors.push(DefinitionError::MissingSource {
+            item: item.id.clone(),
+            path: item_paths[&item.id].clone(),
+        });
+    }
+
+    if errors.is_empty() {
+        Ok((items, recipes))
+    } else {
+        Err(errors)
+    }
+}
+
+/// Keeps the definitions folder loaded so edits are picked up while the game runs.
+#[derive(Resource)]
+pub(super) struct DefinitionsFolder {
+    _folder: Handle<LoadedFolder>,
+}
+
+pub(super) fn load_definitions(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(DefinitionsFolder {
+        _folder: asset_server.load_folder(DEFINITIONS_FOLDER),
+    });
+}
+
+/// Rebuilds the registries whenever a definition file is added, edited or removed.
+///
+/// Broken definitions are reported and the previous registries are kept, so a typo
+/// while iterating doesn't wipe every item from the running game.
+pub(super) fn reload_definitions(
+    mut events: EventReader<AssetEvent<ItemDefinitions>>,
+    definitions: Res<Assets<ItemDefinitions>>,
+    mut items: ResMut<ItemRegistry>,
+    mut recipes: ResMut<RecipeRegistry>,
+) {
+    let changed = events.read().any(|event| {
+        matches!(
+            event,
+            AssetEvent::Added { .. } | AssetEvent::Modified { .. } | AssetEvent::Removed { .. }
+        )
+    });
+    if !changed {
+        return;
+    }
+
+    match build_registries(definitions.iter().map(|(_, file)| file)) {
+        Ok((new_items, new_recipes)) => {
+            info!(
+                "Loaded {} items and {} recipes",
+                new_items.iter().count(),
+                new_recipes.iter().count()
+            );
+            *items = new_items;
+            *recipes = new_recipes;
+        }
+        Err(errors) => {
+            let report = errors
+                .iter()
+                .map(|err| format!("  - {err}"))
+                .collect::<Vec<_>>()
+                .join("\n");
+            error!(
+                "Item definitions have {} problem(s), keeping the previous ones:\n{report}",
+                errors.len()
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logic::item::ItemStack;
+
+    fn item(id: &str, tags: &[&str]) -> ItemDef {
+        ItemDef {
+            id: id.into(),
+            name: id.to_owned(),
+            max_stack: 64,
+            tags: tags.iter().map(|&tag| tag.to_owned()).collect(),
+        }
+    }
+
+    fn recipe(id: &str, inputs: &[&str], outputs: &[&str]) -> Recipe {
+        let stacks = |items: &[&str]| items.iter().map(|&item| ItemStack::new(item, 1)).collect();
+        Recipe {
+            id: id.into(),
+            inputs: stacks(inputs),
+            outputs: stacks(outputs),
+            station: None,
+            craft_time: 1.0,
+        }
+    }
+
+    fn file(path: &str, items: Vec<ItemDef>, recipes: Vec<Recipe>) -> ItemDefinitions {
+        ItemDefinitions {
+            path: path.into(),
+            items,
+            recipes,
+        }
+    }
+
+    #[test]
+    fn valid_files_build_registries() {
+        let files = [
+            file("raw.items.ron", vec![item("log", &[RAW_TAG])], vec![]),
+            file(
+                "wood.items.ron",
+                vec![item("plank", &[])],
+                vec![recipe("planks", &["log"], &["plank"])],
+            ),
+        ];
+
+        let (items, recipes) = build_registries(&files).unwrap();
+        assert!(items.contains(&"plank".into()));
+        assert!(recipes.get(&"planks".into()).is_some());
+    }
+
+    #[test]
+    fn recipes_with_unknown_items_are_reported() {
+        let files = [file(
+            "wood.items.ron",
+            vec![item("plank", &[RAW_TAG])],
+            vec![recipe("planks", &["log"], &["plank"])],
+        )];
+
+        assert_eq!(
+            build_registries(&files).unwrap_err(),
+            vec![DefinitionError::UnknownItem {
+                recipe: "planks".into(),
+                item: "log".into(),
+                path: "wood.items.ron".into(),
+            }]
+        );
+    }
+
+    #[test]
+    fn outputs_listed_twice_are_reported() {
+        let files = [file(
+            "wood.items.ron",
+            vec![item("log", &[RAW_TAG]), item("plank", &[])],
+            vec![recipe("planks", &["log"], &["plank", "plank"])],
+        )];
+
+        assert_eq!(
+            build_registries(&files).unwrap_err(),
+            vec![DefinitionError::DuplicateOutput {
+                recipe: "planks".into(),
+                item: "plank".into(),
+                path: "wood.items.ron".into(),
+            }]
+        );
+    }
+
+    #[test]
+    fn items_defined_in_two_files_are_reported_in_the_second() {
+        let files = [
+            file("raw.items.ron", vec![item("log", &[RAW_TAG])], vec![]),
+            file("wood.items.ron", vec![item("log", &[RAW_TAG])], vec![]),
+        ];
+
+        assert_eq!(
+            build_registries(&files).unwrap_err(),
+            vec![DefinitionError::DuplicateItem {
+                item: "log".into(),
+                path: "wood.items.ron".into(),
+            }]
+        );
+    }
+
+    #[test]
+    fn errors_from_every_file_are_collected() {
+        let mut empty = item("dust", &[RAW_TAG]);
+        empty.max_stack = 0;
+        let files = [
+            file("raw.items.ron", vec![empty, item("gem", &[])], vec![]),
+            file(
+                "tools.items.ron",
+                vec![item("pick", &[])],
+                vec![
+                    recipe("pick", &["stick"], &["pick"]),
+                    recipe("nothing", &["dust"], &[]),
+                ],
+            ),
+        ];
+
+        assert_eq!(
+            build_registries(&files).unwrap_err(),
+            vec![
+                DefinitionError::InvalidStackSize {
+                    item: "dust".into(),
+                    path: "raw.items.ron".into(),
+                },
+                DefinitionError::UnknownItem {
+                    recipe: "pick".into(),
+                    item: "stick".into(),
+                    path: "tools.items.ron".into(),
+                },
+                DefinitionError::NoOutputs {
+                    recipe: "nothing".into(),
+                    path: "tools.items.ron".into(),
+                },
+                DefinitionError::MissingSource {
+                    item: "gem".into(),
+                    path: "raw.items.ron".into(),
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,157 @@
+use bevy::{prelude::*, utils::Duration};
+use bevy_rapier3d::prelude::ColliderDisabled;
+
+use crate::player::Player;
+
+use super::{
+    inventory::Inventory,
+    item::{ItemContainer, ItemRegistry, ItemStack},
+};
+
+/// How close the player has to be to a node to gather from it.
+pub const GATHER_REACH: f32 = 2.5;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum NodeKind {
+    Tree,
+    Rock,
+    Bush,
+}
+
+impl NodeKind {
+    /// Items dropped by a single gather.
+    pub fn drops(self) -> Vec<ItemStack> {
+        match self {
+            NodeKind::Tree => vec![ItemStack::new("log", 1), ItemStack::new("stick", 1)],
+            NodeKind::Rock => vec![ItemStack::new("rock", 2)],
+            NodeKind::Bush => vec![ItemStack::new("berries", 2), ItemStack::new("fiber", 1)],
+        }
+    }
+
+    /// How many times a node can be gathered before it is depleted.
+    pub fn harvests(self) -> u32 {
+        match self {
+            NodeKind::Tree => 4,
+            NodeKind::Rock => 3,
+            NodeKind::Bush => 2,
+        }
+    }
+
+    /// Seconds before a depleted node grows back.
+    pub fn respawn_time(self) -> f32 {
+        match self {
+            NodeKind::Tree => 180.0,
+            NodeKind::Rock => 300.0,
+            NodeKind::Bush => 90.0,
+        }
+    }
+}
+
+/// Something in the world the player can gather items from.
+#[derive(Component, Debug)]
+pub struct ResourceNode {
+    pub kind: NodeKind,
+    pub harvests_left: u32,
+}
+
+impl ResourceNode {
+    pub fn new(kind: NodeKind) -> Self {
+        Self {
+            kind,
+            harvests_left: kind.harvests(),
+        }
+    }
+}
+
+/// Added to nodes with nothing left to gather until they grow back.
+#[derive(Component)]
+pub struct Depleted(pub Timer);
+
+impl Depleted {
+    /// Grows the node back in `remaining` seconds, or its full respawn time if `None`.
+    pub fn new(kind: NodeKind, remaining: Option<f32>) -> Self {
+        let respawn_time = kind.respawn_time();
+        let mut timer = Timer::from_seconds(respawn_time, TimerMode::Once);
+        if let Some(remaining) = remaining {
+            let elapsed = (respawn_time - remaining).clamp(0.0, respawn_time);
+            timer.set_elapsed(Duration::from_secs_f32(elapsed));
+        }
+        Self(timer)
+    }
+}
+
+#[derive(Event, Debug, Clone)]
+pub struct Gathered {
+    pub gatherer: Entity,
+    pub node: Entity,
+    pub kind: NodeKind,
+}
+
+pub(super) fn gather(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    registry: Res<ItemRegistry>,
+    mut player_query: Query<(Entity, &Transform, &mut Inventory), With<Player>>,
+    mut node_query: Query<
+        (Entity, &GlobalTransform, &mut ResourceNode, &mut Visibility),
+        Without<Depleted>,
+    >,
+    mut gathered: EventWriter<Gathered>,
+) {
+    if !keys.just_pressed(KeyCode::E) {
+        return;
+    }
+    let Ok((player_entity, player, mut inventory)) = player_query.get_single_mut() else {
+        return;
+    };
+
+    let closest = node_query
+        .iter_mut()
+        .map(|node| {
+            let distance = node.1.translation().distance(player.translation);
+            (distance, node)
+        })
+        .filter(|(distance, _)| *distance <= GATHER_REACH)
+        .min_by(|(a, _), (b, _)| a.total_cmp(b));
+    let Some((_, (entity, _, mut node, mut visibility))) = closest else {
+        return;
+    };
+
+    let drops = node.kind.drops();
+    if !inventory.can_insert(&registry, &drops) {
+        return;
+    }
+    for stack in drops {
+        inventory.insert(&registry, stack);
+    }
+
+    node.harvests_left = node.harvests_left.saturating_sub(1);
+    if node.harvests_left == 0 {
+        *visibility = Visibility::Hidden;
+        commands
+            .entity(entity)
+            .insert((Depleted::new(node.kind, None), ColliderDisabled));
+    }
+
+    gathered.send(Gathered {
+        gatherer: player_entity,
+        node: entity,
+        kind: node.kind,
+    });
+}
+
+pub(super) fn respawn_nodes(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut node_query: Query<(Entity, &mut ResourceNode, &mut Depleted, &mut Visibility)>,
+) {
+    for (entity, mut node, mut depleted, mut visibility) in node_query.iter_mut() {
+        if depleted.0.tick(time.delta()).finished() {
+            node.harvests_left = node.kind.harvests();
+            *visibility = Visibility::Inherited;
+            commands
+                .entity(entity)
+                .remove::<(Depleted, ColliderDisabled)>();
+        }
+    }
+}
@@ -0,0 +1,500 @@
+use std::fmt;
+
+use bevy::{input::mouse::MouseWheel, prelude::*, utils::HashMap};
+use serde::{Deserialize, Serialize};
+
+use super::item::{ItemContainer, ItemId, ItemRegistry, ItemStack};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InventoryError {
+    InvalidSlot(usize),
+    EmptySlot(usize),
+    /// Tried to split off zero items or the whole stack.
+    InvalidAmount,
+    NoFreeSlot,
+}
+
+impl fmt::Display for InventoryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InventoryError::InvalidSlot(slot) => write!(f, "slot {slot} does not exist"),
+            InventoryError::EmptySlot(slot) => write!(f, "slot {slot} is empty"),
+            InventoryError::InvalidAmount => write!(f, "invalid amount to split"),
+            InventoryError::NoFreeSlot => write!(f, "no free slot"),
+        }
+    }
+}
+
+impl std::error::Error for InventoryError {}
+
+/// Fixed number of slots, each holding up to one item's max stack.
+#[derive(Component, Debug, Clone, Serialize, Deserialize)]
+pub struct Inventory {
+    slots: Vec<Option<ItemStack>>,
+}
+
+impl Inventory {
+    pub fn new(size: usize) -> Self {
+        Self {
+            slots: vec![None; size],
+        }
+    }
+
+    /// Number of slots, occupied or not.
+    pub fn slot_count(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Whether every slot is empty.
+    pub fn is_empty(&self) -> bool {
+        self.slots.iter().all(Option::is_none)
+    }
+
+    pub fn slots(&self) -> &[Option<ItemStack>] {
+        &self.slots
+    }
+
+    pub fn get(&self, slot: usize) -> Option<&ItemStack> {
+        self.slots.get(slot).and_then(Option::as_ref)
+    }
+
+    fn check_slot(&self, slot: usize) -> Result<(), InventoryError> {
+        if slot < self.slots.len() {
+            Ok(())
+        } else {
+            Err(InventoryError::InvalidSlot(slot))
+        }
+    }
+
+    /// Moves `amount` items from `slot` into the first empty slot and returns that slot.
+    pub fn split(&mut self, slot: usize, amount: u32) -> Result<usize, InventoryError> {
+        self.check_slot(slot)?;
+        let stack = self.slots[slot]
+            .as_mut()
+            .ok_or(InventoryError::EmptySlot(slot))?;
+        if amount == 0 || amount >= stack.count {
+            return Err(InventoryError::InvalidAmount);
+        }
+
+        let free = self
+            .slots
+            .iter()
+            .position(Option::is_none)
+            .ok_or(InventoryError::NoFreeSlot)?;
+
+        let stack = self.slots[slot].as_mut().unwrap();
+        stack.count -= amount;
+        self.slots[free] = Some(ItemStack {
+            item: stack.item.clone(),
+            count: amount,
+        });
+        Ok(free)
+    }
+
+    /// Moves the stack in `from` onto `to`.
+    ///
+    /// Stacks of the same item are merged as far as the max stack allows, anything else
+    /// is swapped.
+    pub fn move_stack(
+        &mut self,
+        registry: &ItemRegistry,
+        from: usize,
+        to: usize,
+    ) -> Result<(), InventoryError> {
+        self.check_slot(from)?;
+        self.check_slot(to)?;
+        if self.slots[from].is_none() {
+            return Err(InventoryError::EmptySlot(from));
+        }
+        if from == to {
+            return Ok(());
+        }
+
+        let same_item = match (&self.slots[from], &self.slots[to]) {
+            (Some(a), Some(b)) => a.item == b.item,
+            _ => false,
+        };
+        if !same_item {
+            self.slots.swap(from, to);
+            return Ok(());
+        }
+
+        let max_stack = registry.max_stack(&self.slots[to].as_ref().unwrap().item);
+        let target_count = self.slots[to].as_ref().unwrap().count;
+        let moved = max_stack
+            .saturating_sub(target_count)
+            .min(self.slots[from].as_ref().unwrap().count);
+
+        self.slots[to].as_mut().unwrap().count += moved;
+        let source = self.slots[from].as_mut().unwrap();
+        source.count -= moved;
+        if source.count == 0 {
+            self.slots[from] = None;
+        }
+        Ok(())
+    }
+
+    /// Merges every partial stack of the same item together, keeping slot order.
+    pub fn compact(&mut self, registry: &ItemRegistry) {
+        for to in 0..self.slots.len() {
+            for from in to + 1..self.slots.len() {
+                let same_item = match (&self.slots[to], &self.slots[from]) {
+                    (Some(a), Some(b)) => a.item == b.item,
+                    _ => false,
+                };
+                if same_item {
+                    let _ = self.move_stack(registry, from, to);
+                }
+            }
+        }
+    }
+
+    /// Total count of every item in the inventory.
+    pub fn totals(&self) -> HashMap<ItemId, u32> {
+        let mut totals = HashMap::new();
+        for stack in self.slots.iter().flatten() {
+            *totals.entry(stack.item.clone()).or_default() += stack.count;
+        }
+        totals
+    }
+
+    /// Room left for `item`, counting partial stacks and empty slots.
+    fn space_for(&self, registry: &ItemRegistry, item: &ItemId) -> u32 {
+        let max_stack = registry.max_stack(item);
+        self.slots
+            .iter()
+            .map(|slot| match slot {
+                None => max_stack,
+                Some(stack) if &stack.item == item => max_stack.saturating_sub(stack.count),
+                Some(_) => 0,
+            })
+            .sum()
+    }
+}
+
+impl ItemContainer for Inventory {
+    fn count(&self, item: &ItemId) -> u32 {
+        self.slots
+            .iter()
+            .flatten()
+            .filter(|stack| &stack.item == item)
+            .map(|stack| stack.count)
+            .sum()
+    }
+
+    fn can_insert(&self, registry: &ItemRegistry, stacks: &[ItemStack]) -> bool {
+        // Simulate on a copy so stacks of different items competing for the same
+        // empty slots are accounted for.
+        let mut simulated = self.clone();
+        stacks
+            .iter()
+            .all(|stack| simulated.insert(registry, stack.clone()) == 0)
+    }
+
+    fn insert(&mut self, registry: &ItemRegistry, stack: ItemStack) -> u32 {
+        let max_stack = registry.max_stack(&stack.item);
+        if max_stack == 0 {
+            return stack.count;
+        }
+        let mut remaining = stack.count.min(self.space_for(registry, &stack.item));
+        let leftover = stack.count - remaining;
+
+        // Top up existing stacks first, then fill empty slots.
+        for existing in self.slots.iter_mut().flatten() {
+            if remaining == 0 {
+                break;
+            }
+            if existing.item == stack.item {
+                let moved = max_stack.saturating_sub(existing.count).min(remaining);
+                existing.count += moved;
+                remaining -= moved;
+            }
+        }
+        for slot in self.slots.iter_mut() {
+            if remaining == 0 {
+                break;
+            }
+            if slot.is_none() {
+                let moved = max_stack.min(remaining);
+                *slot = Some(ItemStack {
+                    item: stack.item.clone(),
+                    count: moved,
+                });
+                remaining -= moved;
+            }
+        }
+
+        leftover
+    }
+
+    fn remove(&mut self, item: &ItemId, count: u32) -> u32 {
+        let mut remaining = count;
+
+        // Take from the last stacks first so the hotbar stays filled.
+        for slot in self.slots.iter_mut().rev() {
+            if remaining == 0 {
+                break;
+            }
+            let Some(stack) = slot else {
+                continue;
+            };
+            if &stack.item != item {
+                continue;
+            }
+            let taken = stack.count.min(remaining);
+            stack.count -= taken;
+            remaining -= taken;
+            if stack.count == 0 {
+                *slot = None;
+            }
+        }
+
+        count - remaining
+    }
+}
+
+/// The first `size` slots of an entity's [`Inventory`], one of which is selected.
+#[derive(Component, Debug, Clone)]
+pub struct Hotbar {
+    pub size: usize,
+    pub selected: usize,
+}
+
+impl Hotbar {
+    pub fn new(size: usize) -> Self {
+        Self { size, selected: 0 }
+    }
+
+    pub fn selected_stack<'a>(&self, inventory: &'a Inventory) -> Option<&'a ItemStack> {
+        inventory.get(self.selected)
+    }
+}
+
+#[derive(Event, Debug, Clone)]
+pub struct ItemAdded {
+    pub entity: Entity,
+    pub stack: ItemStack,
+}
+
+#[derive(Event, Debug, Clone)]
+pub struct ItemRemoved {
+    pub entity: Entity,
+    pub stack: ItemStack,
+}
+
+/// Item totals as of the last time events were sent for an inventory.
+#[derive(Component, Default)]
+pub(super) struct InventoryTotals(HashMap<ItemId, u32>);
+
+pub(super) fn track_new_inventories(
+    mut commands: Commands,
+    inventory_query: Query<Entity, (With<Inventory>, Without<InventoryTotals>)>,
+) {
+    for entity in inventory_query.iter() {
+        commands.entity(entity).insert(InventoryTotals::default());
+    }
+}
+
+/// Compares inventories against their previous totals, so events are sent no matter
+/// what changed the inventory.
+pub(super) fn send_inventory_events(
+    mut inventory_query: Query<(Entity, &Inventory, &mut InventoryTotals), Changed<Inventory>>,
+    mut added: EventWriter<ItemAdded>,
+    mut removed: EventWriter<ItemRemoved>,
+) {
+    for (entity, inventory, mut previous) in inventory_query.iter_mut() {
+        let current = inventory.totals();
+        let (gained, lost) = diff_totals(&previous.0, &current);
+        for stack in gained {
+            added.send(ItemAdded { entity, stack });
+        }
+        for stack in lost {
+            removed.send(ItemRemoved { entity, stack });
+        }
+        previous.0 = current;
+    }
+}
+
+/// How much of each item was gained and lost going from `previous` to `current` totals.
+fn diff_totals(
+    previous: &HashMap<ItemId, u32>,
+    current: &HashMap<ItemId, u32>,
+) -> (Vec<ItemStack>, Vec<ItemStack>) {
+    let mut gained = Vec::new();
+    for (item, &count) in current.iter() {
+        let before = previous.get(item).copied().unwrap_or(0);
+        if count > before {
+            gained.push(ItemStack::new(item.clone(), count - before));
+        }
+    }
+
+    let mut lost = Vec::new();
+    for (item, &before) in previous.iter() {
+        let count = current.get(item).copied().unwrap_or(0);
+        if count < before {
+            lost.push(ItemStack::new(item.clone(), before - count));
+        }
+    }
+
+    (gained, lost)
+}
+
+const HOTBAR_KEYS: [KeyCode; 9] = [
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+];
+
+pub(super) fn select_hotbar_slot(
+    keys: Res<Input<KeyCode>>,
+    mut wheel: EventReader<MouseWheel>,
+    mut hotbar_query: Query<&mut Hotbar>,
+) {
+    let scroll: f32 = wheel.read().map(|event| event.y).sum();
+
+    for mut hotbar in hotbar_query.iter_mut() {
+        if hotbar.size == 0 {
+            continue;
+        }
+
+        if let Some(slot) = HOTBAR_KEYS.iter().position(|key| keys.just_pressed(*key)) {
+            if slot < hotbar.size {
+                hotbar.selected = slot;
+            }
+        }
+
+        if scroll != 0.0 {
+            let step = if scroll > 0.0 { hotbar.size - 1 } else { 1 };
+            hotbar.selected = (hotbar.selected + step) % hotbar.size;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Stones stack up to 10, swords don't stack.
+    fn registry() -> ItemRegistry {
+        ItemRegistry::from_ids(&[("stone", 10), ("sword", 1)])
+    }
+
+    fn with_slots(slots: &[Option<(&str, u32)>]) -> Inventory {
+        Inventory {
+            slots: slots
+                .iter()
+                .map(|slot| slot.map(|(item, count)| ItemStack::new(item, count)))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn insert_respects_stack_limits() {
+        let registry = registry();
+        let mut inventory = with_slots(&[Some(("stone", 7)), None, None]);
+
+        assert_eq!(inventory.insert(&registry, ItemStack::new("stone", 25)), 2);
+        assert_eq!(
+            inventory.slots(),
+            &[
+                Some(ItemStack::new("stone", 10)),
+                Some(ItemStack::new("stone", 10)),
+                Some(ItemStack::new("stone", 10)),
+            ]
+        );
+        assert_eq!(inventory.insert(&registry, ItemStack::new("unknown", 1)), 1);
+    }
+
+    #[test]
+    fn can_insert_accounts_for_shared_free_slots() {
+        let registry = registry();
+        let inventory = with_slots(&[Some(("stone", 5)), None]);
+
+        assert!(inventory.can_insert(&registry, &[ItemStack::new("stone", 5)]));
+        assert!(inventory.can_insert(&registry, &[ItemStack::new("sword", 1)]));
+        assert!(!inventory.can_insert(
+            &registry,
+            &[ItemStack::new("sword", 1), ItemStack::new("sword", 1)]
+        ));
+    }
+
+    #[test]
+    fn split_moves_part_of_a_stack_to_a_free_slot() {
+        let mut inventory = with_slots(&[Some(("stone", 8)), Some(("sword", 1)), None]);
+
+        assert_eq!(inventory.split(0, 3), Ok(2));
+        assert_eq!(inventory.get(0), Some(&ItemStack::new("stone", 5)));
+        assert_eq!(inventory.get(2), Some(&ItemStack::new("stone", 3)));
+
+        assert_eq!(inventory.split(0, 0), Err(InventoryError::InvalidAmount));
+        assert_eq!(inventory.split(0, 5), Err(InventoryError::InvalidAmount));
+        assert_eq!(inventory.split(0, 1), Err(InventoryError::NoFreeSlot));
+        assert_eq!(inventory.split(3, 1), Err(InventoryError::InvalidSlot(3)));
+    }
+
+    #[test]
+    fn move_stack_merges_up_to_the_limit_or_swaps() {
+        let registry = registry();
+        let mut inventory =
+            with_slots(&[Some(("stone", 6)), Some(("stone", 8)), Some(("sword", 1))]);
+
+        inventory.move_stack(&registry, 0, 1).unwrap();
+        assert_eq!(inventory.get(0), Some(&ItemStack::new("stone", 4)));
+        assert_eq!(inventory.get(1), Some(&ItemStack::new("stone", 10)));
+
+        inventory.move_stack(&registry, 2, 0).unwrap();
+        assert_eq!(inventory.get(0), Some(&ItemStack::new("sword", 1)));
+        assert_eq!(inventory.get(2), Some(&ItemStack::new("stone", 4)));
+
+        let mut inventory = with_slots(&[Some(("stone", 3)), None]);
+        inventory.move_stack(&registry, 0, 1).unwrap();
+        assert_eq!(inventory.slots(), &[None, Some(ItemStack::new("stone", 3))]);
+        assert_eq!(
+            inventory.move_stack(&registry, 0, 1),
+            Err(InventoryError::EmptySlot(0))
+        );
+    }
+
+    #[test]
+    fn compact_merges_partial_stacks_without_moving_them() {
+        let registry = registry();
+        let mut inventory = with_slots(&[
+            Some(("stone", 4)),
+            Some(("sword", 1)),
+            Some(("stone", 4)),
+            Some(("stone", 4)),
+        ]);
+
+        inventory.compact(&registry);
+        assert_eq!(
+            inventory.slots(),
+            &[
+                Some(ItemStack::new("stone", 10)),
+                Some(ItemStack::new("sword", 1)),
+                None,
+                Some(ItemStack::new("stone", 2)),
+            ]
+        );
+        assert_eq!(inventory.slot_count(), 4);
+        assert!(!inventory.is_empty());
+    }
+
+    #[test]
+    fn totals_diff_reports_gains_and_losses() {
+        let previous: HashMap<ItemId, u32> = [("stone".into(), 5), ("sword".into(), 1)].into();
+        let current: HashMap<ItemId, u32> = [("stone".into(), 8)].into();
+
+        let (gained, lost) = diff_totals(&previous, &current);
+        assert_eq!(gained, vec![ItemStack::new("stone", 3)]);
+        assert_eq!(lost, vec![ItemStack::new("sword", 1)]);
+
+        let (gained, lost) = diff_totals(&current, &current);
+        assert!(gained.is_empty() && lost.is_empty());
+    }
+}
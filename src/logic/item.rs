@@ -0,0 +1,187 @@
+use std::fmt;
+
+use bevy::{ecs::system::Resource, utils::HashMap};
+use serde::{Deserialize, Serialize};
+
+use super::crafting::RecipeId;
+
+/// Stack size used when an item definition doesn't specify one.
+pub const DEFAULT_MAX_STACK: u32 = 64;
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ItemId(pub String);
+
+impl From<&str> for ItemId {
+    fn from(value: &str) -> Self {
+        Self(value.to_owned())
+    }
+}
+
+impl fmt::Display for ItemId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ItemDef {
+    pub id: ItemId,
+    pub name: String,
+    /// How many of the item fit into a single inventory slot.
+    #[serde(default = "default_max_stack")]
+    pub max_stack: u32,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+fn default_max_stack() -> u32 {
+    DEFAULT_MAX_STACK
+}
+
+impl ItemDef {
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t == tag)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ItemStack {
+    pub item: ItemId,
+    pub count: u32,
+}
+
+impl ItemStack {
+    pub fn new(item: impl Into<ItemId>, count: u32) -> Self {
+        Self {
+            item: item.into(),
+            count,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RegistryError {
+    DuplicateItem(ItemId),
+    DuplicateRecipe(RecipeId),
+    UnknownItem(ItemId),
+}
+
+impl fmt::Display for RegistryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RegistryError::DuplicateItem(id) => write!(f, "item `{id}` is registered twice"),
+            RegistryError::DuplicateRecipe(id) => {
+                write!(f, "recipe `{id}` is registered twice")
+            }
+            RegistryError::UnknownItem(id) => write!(f, "unknown item `{id}`"),
+        }
+    }
+}
+
+impl std::error::Error for RegistryError {}
+
+/// Every item that exists in the game, by id.
+#[derive(Resource, Default, Debug)]
+pub struct ItemRegistry {
+    items: HashMap<ItemId, ItemDef>,
+}
+
+impl ItemRegistry {
+    pub fn register(&mut self, item: ItemDef) -> Result<(), RegistryError> {
+        if self.items.contains_key(&item.id) {
+            return Err(RegistryError::DuplicateItem(item.id));
+        }
+        self.items.insert(item.id.clone(), item);
+        Ok(())
+    }
+
+    pub fn get(&self, id: &ItemId) -> Option<&ItemDef> {
+        self.items.get(id)
+    }
+
+    pub fn contains(&self, id: &ItemId) -> bool {
+        self.items.contains_key(id)
+    }
+
+    /// Stack size of an item, `0` if it is unknown.
+    pub fn max_stack(&self, id: &ItemId) -> u32 {
+        self.get(id).map_or(0, |item| item.max_stack)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &ItemDef> {
+        self.items.values()
+    }
+
+    pub fn with_tag<'a>(&'a self, tag: &'a str) -> impl Iterator<Item = &'a ItemDef> {
+        self.iter().filter(move |item| item.has_tag(tag))
+    }
+}
+
+#[cfg(test)]
+impl ItemRegistry {
+    /// Registry of untagged items named after their ids, each with its max stack.
+    pub fn from_ids(items: &[(&str, u32)]) -> Self {
+        let mut registry = Self::default();
+        for &(id, max_stack) in items {
+            registry
+                .register(ItemDef {
+                    id: id.into(),
+                    name: id.to_owned(),
+                    max_stack,
+                    tags: Vec::new(),
+                })
+                .unwrap();
+        }
+        registry
+    }
+}
+
+/// Anything items can be taken out of and put into.
+pub trait ItemContainer {
+    fn count(&self, item: &ItemId) -> u32;
+
+    /// Whether all `stacks` would fit at once.
+    fn can_insert(&self, registry: &ItemRegistry, stacks: &[ItemStack]) -> bool;
+
+    /// Inserts as much of the stack as fits and returns what was left over.
+    fn insert(&mut self, registry: &ItemRegistry, stack: ItemStack) -> u32;
+
+    /// Removes up to `count` items and returns how many were removed.
+    fn remove(&mut self, item: &ItemId, count: u32) -> u32;
+
+    fn contains(&self, stack: &ItemStack) -> bool {
+        self.count(&stack.item) >= stack.count
+    }
+}
+
+/// An unbounded bag of items, useful for stations and tests that don't care about slots.
+impl ItemContainer for HashMap<ItemId, u32> {
+    fn count(&self, item: &ItemId) -> u32 {
+        self.get(item).copied().unwrap_or(0)
+    }
+
+    fn can_insert(&self, registry: &ItemRegistry, stacks: &[ItemStack]) -> bool {
+        stacks.iter().all(|stack| registry.contains(&stack.item))
+    }
+
+    fn insert(&mut self, registry: &ItemRegistry, stack: ItemStack) -> u32 {
+        if !registry.contains(&stack.item) {
+            return stack.count;
+        }
+        *self.entry(stack.item).or_default() += stack.count;
+        0
+    }
+
+    fn remove(&mut self, item: &ItemId, count: u32) -> u32 {
+        let Some(available) = self.get_mut(item) else {
+            return 0;
+        };
+        let removed = count.min(*available);
+        *available -= removed;
+        if *available == 0 {
+            HashMap::remove(self, item);
+        }
+        removed
+    }
+}
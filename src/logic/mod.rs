@@ -1 +1,62 @@
+use bevy::{
+    app::{App, Plugin, PostUpdate, Startup, Update},
+    asset::AssetApp,
+    ecs::{
+        change_detection::DetectChanges,
+        schedule::IntoSystemConfigs,
+        system::{Res, ResMut},
+    },
+};
+
+use crafting::RecipeRegistry;
+use definitions::{ItemDefinitions, ItemDefinitionsLoader};
+use gathering::Gathered;
+use inventory::{ItemAdded, ItemRemoved};
+use item::ItemRegistry;
+use recipe_graph::RecipeGraph;
+
 pub mod crafting;
+pub mod definitions;
+pub mod gathering;
+pub mod inventory;
+pub mod item;
+pub mod recipe_graph;
+
+pub struct LogicPlugin;
+
+impl Plugin for LogicPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<ItemDefinitions>()
+            .init_asset_loader::<ItemDefinitionsLoader>()
+            .init_resource::<ItemRegistry>()
+            .init_resource::<RecipeRegistry>()
+            .init_resource::<RecipeGraph>()
+            .add_event::<ItemAdded>()
+            .add_event::<ItemRemoved>()
+            .add_event::<Gathered>()
+            .add_systems(Startup, definitions::load_definitions)
+            .add_systems(
+                Update,
+                (
+                    (definitions::reload_definitions, rebuild_recipe_graph).chain(),
+                    inventory::select_hotbar_slot,
+                    gathering::gather,
+                    gathering::respawn_nodes,
+                ),
+            )
+            .add_systems(
+                PostUpdate,
+                (
+                    inventory::track_new_inventories,
+                    inventory::send_inventory_events,
+                )
+                    .chain(),
+            );
+    }
+}
+
+fn rebuild_recipe_graph(recipes: Res<RecipeRegistry>, mut graph: ResMut<RecipeGraph>) {
+    if recipes.is_changed() {
+        *graph = RecipeGraph::new(&recipes);
+    }
+}
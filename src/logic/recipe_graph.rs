@@ -0,0 +1,507 @@
+use bevy::{
+    ecs::system::Resource,
+    utils::{HashMap, HashSet},
+};
+
+use super::{
+    crafting::{can_craft, Recipe, RecipeId, RecipeRegistry, StationId},
+    item::{ItemContainer, ItemId, ItemRegistry},
+};
+
+/// Items and recipes as a graph, where each recipe links its inputs to its outputs.
+#[derive(Resource, Default, Debug)]
+pub struct RecipeGraph {
+    recipes: HashMap<RecipeId, Recipe>,
+    /// Recipes that produce an item.
+    producers: HashMap<ItemId, Vec<RecipeId>>,
+    /// Items an item is directly crafted into.
+    edges: HashMap<ItemId, HashSet<ItemId>>,
+}
+
+/// How to obtain an item, down to raw materials.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MaterialTree {
+    pub item: ItemId,
+    pub count: u32,
+    /// Recipe used to craft the item, `None` for raw materials.
+    pub recipe: Option<RecipeId>,
+    pub inputs: Vec<MaterialTree>,
+}
+
+impl MaterialTree {
+    /// Total amount of each raw material in the tree.
+    pub fn raw_materials(&self) -> HashMap<ItemId, u32> {
+        let mut totals = HashMap::new();
+        self.collect_raw(&mut totals);
+        totals
+    }
+
+    fn collect_raw(&self, totals: &mut HashMap<ItemId, u32>) {
+        if self.recipe.is_none() {
+            *totals.entry(self.item.clone()).or_default() += self.count;
+        }
+        for input in &self.inputs {
+            input.collect_raw(totals);
+        }
+    }
+}
+
+impl RecipeGraph {
+    pub fn new(registry: &RecipeRegistry) -> Self {
+        let mut graph = Self::default();
+
+        for recipe in registry.iter() {
+            for output in &recipe.outputs {
+                graph
+                    .producers
+                    .entry(output.item.clone())
+                    .or_default()
+                    .push(recipe.id.clone());
+                for input in &recipe.inputs {
+                    graph
+                        .edges
+                        .entry(input.item.clone())
+                        .or_default()
+                        .insert(output.item.clone());
+                }
+            }
+            graph.recipes.insert(recipe.id.clone(), recipe.clone());
+        }
+
+        // Keep the order stable so trees and paths don't change between runs.
+        for producers in graph.producers.values_mut() {
+            producers.sort();
+        }
+
+        graph
+    }
+
+    /// Whether an item can't be crafted and has to be gathered.
+    pub fn is_raw(&self, item: &ItemId) -> bool {
+        !self.producers.contains_key(item)
+    }
+
+    pub fn producers(&self, item: &ItemId) -> &[RecipeId] {
+        self.producers.get(item).map_or(&[], Vec::as_slice)
+    }
+
+    /// Recipes that can be crafted right now from `container`.
+    pub fn craftable(
+        &self,
+        items: &ItemRegistry,
+        container: &(impl ItemContainer + Clone),
+        stations: &[StationId],
+    ) -> Vec<&Recipe> {
+        let mut craftable: Vec<&Recipe> = self
+            .recipes
+            .values()
+            .filter(|recipe| can_craft(items, recipe, container, stations).is_ok())
+            .collect();
+        craftable.sort_by(|a, b| a.id.cmp(&b.id));
+        craftable
+    }
+
+    /// Builds the full tree of what it takes to craft `count` of `item`.
+    ///
+    /// Picks the recipe with the shortest crafting path for every intermediate item.
+    /// Returns `None` if the item can only be made through a cycle.
+    pub fn material_tree(&self, item: &ItemId, count: u32) -> Option<MaterialTree> {
+        let costs = self.crafting_costs(|_| false);
+        self.build_tree(item, count, &costs)
+    }
+
+    /// The recipe `costs` settled on for an item, `Some(None)` if it isn't crafted and
+    /// `None` if it can only be made through a cycle.
+    fn best_recipe<'a>(
+        &self,
+        item: &ItemId,
+        costs: &'a HashMap<ItemId, (u32, Option<RecipeId>)>,
+    ) -> Option<Option<&'a RecipeId>> {
+        match costs.get(item) {
+            Some((_, recipe_id)) => Some(recipe_id.as_ref()),
+            // Items no recipe mentions aren't in the costs, but are just as raw.
+            None => self.is_raw(item).then_some(None),
+        }
+    }
+
+    fn build_tree(
+        &self,
+        item: &ItemId,
+        count: u32,
+        costs: &HashMap<ItemId, (u32, Option<RecipeId>)>,
+    ) -> Option<MaterialTree> {
+        let Some(recipe_id) = self.best_recipe(item, costs)? else {
+            return Some(MaterialTree {
+                item: item.clone(),
+                count,
+                recipe: None,
+                inputs: Vec::new(),
+            });
+        };
+
+        let recipe = &self.recipes[recipe_id];
+        let produced = recipe
+            .outputs
+            .iter()
+            .filter(|stack| &stack.item == item)
+            .map(|stack| stack.count)
+            .sum::<u32>()
+            .max(1);
+        let crafts = count.div_ceil(produced);
+
+        let inputs = recipe
+            .inputs
+            .iter()
+            .map(|stack| self.build_tree(&stack.item, stack.count * crafts, costs))
+            .collect::<Option<Vec<_>>>()?;
+
+        Some(MaterialTree {
+            item: item.clone(),
+            count,
+            recipe: Some(recipe_id.clone()),
+            inputs,
+        })
+    }
+
+    /// Shortest list of crafts, in order, that ends with `item`.
+    ///
+    /// Items for which `available` returns `true` are treated as already at hand, raw
+    /// materials are assumed to be gathered. Returns an empty list if nothing needs
+    /// crafting and `None` if `item` can't be obtained at all.
+    pub fn shortest_path(
+        &self,
+        item: &ItemId,
+        available: impl Fn(&ItemId) -> bool,
+    ) -> Option<Vec<RecipeId>> {
+        let costs = self.crafting_costs(available);
+        let mut path = Vec::new();
+        self.collect_path(item, &costs, &mut path)?;
+        Some(path)
+    }
+
+    fn collect_path(
+        &self,
+        item: &ItemId,
+        costs: &HashMap<ItemId, (u32, Option<RecipeId>)>,
+        path: &mut Vec<RecipeId>,
+    ) -> Option<()> {
+        if let Some(recipe_id) = self.best_recipe(item, costs)? {
+            // Inputs sharing an ingredient only need it crafted once.
+            if path.contains(recipe_id) {
+                return Some(());
+            }
+            for input in &self.recipes[recipe_id].inputs {
+                self.collect_path(&input.item, costs, path)?;
+            }
+            path.push(recipe_id.clone());
+        }
+        Some(())
+    }
+
+    /// Number of crafts needed to obtain every reachable item and the best recipe for it.
+    ///
+    /// Raw and available items cost nothing, a recipe costs one plus the cost of its
+    /// inputs. Items stuck behind a cycle never settle and are left out.
+    fn crafting_costs(
+        &self,
+        available: impl Fn(&ItemId) -> bool,
+    ) -> HashMap<ItemId, (u32, Option<RecipeId>)> {
+        let mut costs: HashMap<ItemId, (u32, Option<RecipeId>)> = HashMap::new();
+
+        let all_items = self
+            .edges
+            .keys()
+            .chain(self.producers.keys())
+            .collect::<HashSet<_>>();
+        for item in all_items {
+            if self.is_raw(item) || available(item) {
+                costs.insert(item.clone(), (0, None));
+            }
+        }
+
+        let mut recipe_ids: Vec<&RecipeId> = self.recipes.keys().collect();
+        recipe_ids.sort();
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for recipe_id in &recipe_ids {
+                let recipe = &self.recipes[*recipe_id];
+                let inputs_cost = recipe
+                    .inputs
+                    .iter()
+                    .map(|stack| costs.get(&stack.item).map(|(cost, _)| *cost))
+                    .sum::<Option<u32>>();
+                let Some(inputs_cost) = inputs_cost else {
+                    continue;
+                };
+
+                let cost = inputs_cost + 1;
+                for output in &recipe.outputs {
+                    let better = match costs.get(&output.item) {
+                        Some((current, _)) => cost < *current,
+                        None => true,
+                    };
+                    if better {
+                        costs.insert(output.item.clone(), (cost, Some((*recipe_id).clone())));
+                        changed = true;
+                    }
+                }
+            }
+        }
+
+        costs
+    }
+
+    /// Groups of items that can be crafted back into themselves.
+    pub fn cycles(&self) -> Vec<Vec<ItemId>> {
+        let mut tarjan = Tarjan::default();
+
+        let mut items: Vec<&ItemId> = self.edges.keys().collect();
+        items.sort();
+        for item in items {
+            if !tarjan.indices.contains_key(item) {
+                tarjan.visit(self, item);
+            }
+        }
+
+        tarjan
+            .components
+            .into_iter()
+            .filter(|component| {
+                component.len() > 1
+                    || self
+                        .edges
+                        .get(&component[0])
+                        .is_some_and(|outputs| outputs.contains(&component[0]))
+            })
+            .map(|mut component| {
+                component.sort();
+                component
+            })
+            .collect()
+    }
+}
+
+/// Tarjan's strongly connected components over the item graph.
+#[derive(Default)]
+struct Tarjan {
+    index: usize,
+    indices: HashMap<ItemId, usize>,
+    low_links: HashMap<ItemId, usize>,
+    stack: Vec<ItemId>,
+    on_stack: HashSet<ItemId>,
+    components: Vec<Vec<ItemId>>,
+}
+
+impl Tarjan {
+    fn visit(&mut self, graph: &RecipeGraph, item: &ItemId) {
+        self.indices.insert(item.clone(), self.index);
+        self.low_links.insert(item.clone(), self.index);
+        self.index += 1;
+        self.stack.push(item.clone());
+        self.on_stack.insert(item.clone());
+
+        let mut outputs: Vec<&ItemId> = graph
+            .edges
+            .get(item)
+            .map(|outputs| outputs.iter().collect())
+            .unwrap_or_default();
+        outputs.sort();
+
+        for output in outputs {
+            if !self.indices.contains_key(output) {
+                self.visit(graph, output);
+                let low = self.low_links[item].min(self.low_links[output]);
+                self.low_links.insert(item.clone(), low);
+            } else if self.on_stack.contains(output) {
+                let low = self.low_links[item].min(self.indices[output]);
+                self.low_links.insert(item.clone(), low);
+            }
+        }
+
+        if self.low_links[item] == self.indices[item] {
+            let mut component = Vec::new();
+            while let Some(member) = self.stack.pop() {
+                self.on_stack.remove(&member);
+                let done = &member == item;
+                component.push(member);
+                if done {
+                    break;
+                }
+            }
+            self.components.push(component);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logic::item::ItemStack;
+
+    fn recipe(id: &str, inputs: &[(&str, u32)], outputs: &[(&str, u32)]) -> Recipe {
+        let stacks = |stacks: &[(&str, u32)]| {
+            stacks
+                .iter()
+                .map(|&(item, count)| ItemStack::new(item, count))
+                .collect()
+        };
+        Recipe {
+            id: id.into(),
+            inputs: stacks(inputs),
+            outputs: stacks(outputs),
+            station: None,
+            craft_time: 1.0,
+        }
+    }
+
+    /// A plank chain, two ways to make rope, ingots that can be broken into nuggets and
+    /// back, relics that only exist in a cycle with their shards, circuits made from two
+    /// parts that both need wire, and pebbles no recipe uses.
+    fn registries() -> (ItemRegistry, RecipeRegistry) {
+        let ids = [
+            "log", "plank", "stick", "coal", "torch", "fiber", "rope", "ore", "ingot", "nugget",
+            "relic", "shard", "copper", "wire", "coil", "pin", "circuit", "pebble",
+        ];
+        let items = ItemRegistry::from_ids(&ids.map(|id| (id, 64)));
+
+        let mut smelt = recipe("smelt", &[("ore", 1)], &[("ingot", 1)]);
+        smelt.station = Some("furnace".into());
+        let mut recipes = RecipeRegistry::default();
+        for recipe in [
+            recipe("planks", &[("log", 1)], &[("plank", 4)]),
+            recipe("sticks", &[("plank", 2)], &[("stick", 4)]),
+            recipe("torch", &[("stick", 1), ("coal", 1)], &[("torch", 4)]),
+            recipe("rope_from_fiber", &[("fiber", 3)], &[("rope", 1)]),
+            recipe("rope_from_sticks", &[("stick", 4)], &[("rope", 1)]),
+            smelt,
+            recipe("break_ingot", &[("ingot", 1)], &[("nugget", 9)]),
+            recipe("join_nuggets", &[("nugget", 9)], &[("ingot", 1)]),
+            recipe("break_relic", &[("relic", 1)], &[("shard", 3)]),
+            recipe("join_shards", &[("shard", 3)], &[("relic", 1)]),
+            recipe("wire", &[("copper", 1)], &[("wire", 2)]),
+            recipe("coil", &[("wire", 1)], &[("coil", 1)]),
+            recipe("pin", &[("wire", 1)], &[("pin", 1)]),
+            recipe("circuit", &[("coil", 1), ("pin", 1)], &[("circuit", 1)]),
+        ] {
+            recipes.register(&items, recipe).unwrap();
+        }
+        (items, recipes)
+    }
+
+    #[test]
+    fn craftable_needs_inputs_and_stations() {
+        let (items, recipes) = registries();
+        let graph = RecipeGraph::new(&recipes);
+        let container: HashMap<ItemId, u32> = [("log".into(), 1), ("ore".into(), 1)].into();
+
+        let ids = |recipes: Vec<&Recipe>| -> Vec<RecipeId> {
+            recipes
+                .into_iter()
+                .map(|recipe| recipe.id.clone())
+                .collect()
+        };
+        assert_eq!(
+            ids(graph.craftable(&items, &container, &[])),
+            vec![RecipeId::from("planks")]
+        );
+        assert_eq!(
+            ids(graph.craftable(&items, &container, &["furnace".into()])),
+            vec![RecipeId::from("planks"), RecipeId::from("smelt")]
+        );
+    }
+
+    #[test]
+    fn material_tree_follows_a_linear_chain() {
+        let (_, recipes) = registries();
+        let graph = RecipeGraph::new(&recipes);
+
+        let tree = graph.material_tree(&"torch".into(), 8).unwrap();
+        assert_eq!(tree.recipe, Some("torch".into()));
+        // Two crafts of torches take two sticks, which one craft of sticks makes from two
+        // planks out of a single log.
+        let expected: HashMap<ItemId, u32> = [("log".into(), 1), ("coal".into(), 2)].into();
+        assert_eq!(tree.raw_materials(), expected);
+    }
+
+    #[test]
+    fn shortest_path_picks_the_cheapest_recipe() {
+        let (_, recipes) = registries();
+        let graph = RecipeGraph::new(&recipes);
+
+        assert_eq!(
+            graph.shortest_path(&"torch".into(), |_| false),
+            Some(vec!["planks".into(), "sticks".into(), "torch".into()])
+        );
+        assert_eq!(
+            graph.shortest_path(&"rope".into(), |_| false),
+            Some(vec!["rope_from_fiber".into()])
+        );
+        assert_eq!(
+            graph.shortest_path(&"torch".into(), |item| item.0 == "stick"),
+            Some(vec!["torch".into()])
+        );
+        assert_eq!(graph.shortest_path(&"log".into(), |_| false), Some(vec![]));
+    }
+
+    #[test]
+    fn shared_ingredients_are_crafted_once() {
+        let (_, recipes) = registries();
+        let graph = RecipeGraph::new(&recipes);
+
+        assert_eq!(
+            graph.shortest_path(&"circuit".into(), |_| false),
+            Some(vec![
+                "wire".into(),
+                "coil".into(),
+                "pin".into(),
+                "circuit".into()
+            ])
+        );
+    }
+
+    #[test]
+    fn items_no_recipe_uses_are_raw() {
+        let (_, recipes) = registries();
+        let graph = RecipeGraph::new(&recipes);
+
+        let tree = graph.material_tree(&"pebble".into(), 3).unwrap();
+        assert_eq!(tree.recipe, None);
+        assert_eq!(tree.raw_materials(), [("pebble".into(), 3)].into());
+        assert_eq!(
+            graph.shortest_path(&"pebble".into(), |_| false),
+            Some(vec![])
+        );
+    }
+
+    #[test]
+    fn items_behind_a_cycle_are_unreachable() {
+        let (_, recipes) = registries();
+        let graph = RecipeGraph::new(&recipes);
+
+        assert_eq!(graph.material_tree(&"relic".into(), 1), None);
+        assert_eq!(graph.shortest_path(&"shard".into(), |_| false), None);
+        // Nuggets cycle with ingots too, but ingots can also be smelted.
+        assert_eq!(
+            graph.shortest_path(&"nugget".into(), |_| false),
+            Some(vec!["smelt".into(), "break_ingot".into()])
+        );
+    }
+
+    #[test]
+    fn cycles_are_found() {
+        let (_, recipes) = registries();
+        let graph = RecipeGraph::new(&recipes);
+
+        let mut cycles = graph.cycles();
+        cycles.sort();
+        assert_eq!(
+            cycles,
+            vec![
+                vec![ItemId::from("ingot"), ItemId::from("nugget")],
+                vec![ItemId::from("relic"), ItemId::from("shard")],
+            ]
+        );
+    }
+}
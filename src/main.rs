@@ -6,7 +6,10 @@ use bevy_third_person_camera::ThirdPersonCameraPlugin;
 
 use camera::CameraPlugin;
 use gen::MapPlugin;
+use logic::LogicPlugin;
 use player::PlayerPlugin;
+use save::SavePlugin;
+use water::WaterPlugin;
 use world::WorldPlugin;
 
 pub mod gen;
@@ -15,17 +18,27 @@ pub mod utils;
 
 pub mod camera;
 pub mod player;
+pub mod save;
+pub mod water;
 pub mod world;
 
 fn main() {
     App::new()
-        .add_plugins(DefaultPlugins.set(WindowPlugin {
-            primary_window: Some(Window {
-                title: "Zero-Point".to_owned(),
-                ..Default::default()
-            }),
-            ..Default::default()
-        }))
+        .add_plugins(
+            DefaultPlugins
+                .set(WindowPlugin {
+                    primary_window: Some(Window {
+                        title: "Zero-Point".to_owned(),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                })
+                .set(AssetPlugin {
+                    // Lets item definitions and other data files be edited while the game runs
+                    watch_for_changes_override: Some(true),
+                    ..Default::default()
+                }),
+        )
         .add_plugins((
             RapierPhysicsPlugin::<NoUserData>::default(),
             RapierDebugRenderPlugin::default(),
@@ -33,7 +46,15 @@ fn main() {
         .add_plugins(WorldInspectorPlugin::new())
         .add_plugins(ThirdPersonCameraPlugin)
         .add_plugins(AtmospherePlugin)
-        .add_plugins((WorldPlugin, CameraPlugin, PlayerPlugin, MapPlugin))
+        .add_plugins((
+            WorldPlugin,
+            CameraPlugin,
+            PlayerPlugin,
+            MapPlugin,
+            WaterPlugin,
+            LogicPlugin,
+            SavePlugin,
+        ))
         .run()
 }
 
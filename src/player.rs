@@ -2,28 +2,160 @@ use bevy::prelude::*;
 use bevy_rapier3d::prelude::*;
 use bevy_third_person_camera::ThirdPersonCameraTarget;
 
+use crate::{
+    camera::CameraMode,
+    gen::{
+        chunk::{ChunkAnchor, ChunkLoaded, Terrain},
+        spawn::SpawnSearch,
+        NoiseConfig,
+    },
+    logic::inventory::{Hotbar, Inventory},
+    water::Swimming,
+};
+
 pub struct PlayerPlugin;
 
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, spawn_player)
-            .add_systems(Update, player_movement);
+        app.register_type::<MovementSettings>()
+            .init_resource::<SpawnSettings>()
+            .register_type::<SpawnSettings>()
+            .add_event::<PlayerDied>()
+            .add_systems(Startup, spawn_player)
+            .add_systems(Update, (fall_check, respawn_player, place_player).chain())
+            .add_systems(
+                Update,
+                (ground_check, player_movement)
+                    .chain()
+                    .run_if(not(in_state(CameraMode::FreeFly))),
+            );
     }
 }
 
+/// Half of the player's cube size, used for the collider and ground probing.
+const PLAYER_HALF_EXTENT: f32 = 0.5;
+
+/// Frames without newly loaded chunks before the player is placed, since colliders of a
+/// chunk and of the structures on it take a few frames to reach the physics world.
+const SETTLE_FRAMES: u32 = 3;
+/// How far above a candidate surface the ground below it is probed from.
+const GROUND_PROBE: f32 = 4.0;
+/// Largest difference between the generated and the hit surface that still counts as ground.
+const GROUND_TOLERANCE: f32 = 0.5;
+
 #[derive(Component)]
 pub struct Player;
 
+/// Where and on what kind of ground the player is (re)spawned.
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+pub struct SpawnSettings {
+    /// World position on the XZ plane the search starts from.
+    pub point: Vec2,
+    /// Cells around `point` searched for safe ground.
+    pub search_radius: u32,
+    /// Steepest ground the player is put on, in world units per cell.
+    pub max_slope: f32,
+    /// World height below which the player has fallen out of the world.
+    pub fall_height: f32,
+}
+
+impl Default for SpawnSettings {
+    fn default() -> Self {
+        Self {
+            point: Vec2::ZERO,
+            search_radius: 64,
+            max_slope: 0.5,
+            fall_height: -32.0,
+        }
+    }
+}
+
+/// Send to put the player back at the spawn point.
+#[derive(Event)]
+pub struct PlayerDied;
+
+/// Held above the spawn point with physics disabled until safe ground is found, keeping
+/// the chunks around it loaded.
+#[derive(Component)]
+pub struct Respawning;
+
 #[derive(Component)]
 pub struct MovementSpeed(pub f32);
 
+/// Controls how the player gains and loses speed and how fast it turns.
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct MovementSettings {
+    /// Speed gained per second while there is movement input.
+    pub acceleration: f32,
+    /// Speed lost per second once the input is released.
+    pub deceleration: f32,
+    /// Multiplier applied to deceleration while standing on the ground.
+    pub ground_friction: f32,
+    /// Multiplier applied to deceleration while in the air.
+    pub air_friction: f32,
+    /// Fraction of `acceleration` available while in the air.
+    pub air_control: f32,
+    /// How quickly the player rotates towards the movement direction.
+    pub turn_rate: f32,
+    /// Fraction of `MovementSpeed` available while swimming.
+    pub swim_speed_factor: f32,
+}
+
+impl Default for MovementSettings {
+    fn default() -> Self {
+        Self {
+            acceleration: 12.0,
+            deceleration: 10.0,
+            ground_friction: 1.0,
+            air_friction: 0.1,
+            air_control: 0.3,
+            turn_rate: 10.0,
+            swim_speed_factor: 0.6,
+        }
+    }
+}
+
+#[derive(Component, Default)]
+pub struct Grounded(pub bool);
+
+fn ground_check(
+    rapier_context: Res<RapierContext>,
+    mut player_query: Query<(Entity, &Transform, &mut Grounded), With<Player>>,
+) {
+    for (entity, transform, mut grounded) in player_query.iter_mut() {
+        let hit = rapier_context.cast_ray(
+            transform.translation,
+            Vec3::NEG_Y,
+            PLAYER_HALF_EXTENT + 0.1,
+            true,
+            QueryFilter::default().exclude_rigid_body(entity),
+        );
+        grounded.0 = hit.is_some();
+    }
+}
+
+#[allow(clippy::type_complexity)]
 fn player_movement(
     keys: Res<Input<KeyCode>>,
     time: Res<Time>,
-    mut player_query: Query<(&mut Transform, &MovementSpeed), With<Player>>,
+    mut player_query: Query<
+        (
+            &mut Transform,
+            &mut Velocity,
+            &MovementSpeed,
+            &MovementSettings,
+            &Grounded,
+            Option<&Swimming>,
+        ),
+        With<Player>,
+    >,
     camera_query: Query<&Transform, (With<Camera3d>, Without<Player>)>,
 ) {
-    for (mut player_transform, player_speed) in player_query.iter_mut() {
+    for (mut player_transform, mut velocity, player_speed, settings, grounded, swimming) in
+        player_query.iter_mut()
+    {
         let camera = camera_query.get_single().unwrap();
 
         let mut direction = Vec3::ZERO;
@@ -46,31 +178,204 @@ fn player_movement(
         // }
 
         direction.y = 0.0;
-        let movement = direction.normalize_or_zero() * player_speed.0 * time.delta_seconds();
-        player_transform.translation += movement;
+        let direction = direction.normalize_or_zero();
+        let delta = time.delta_seconds();
+
+        let horizontal = Vec2::new(velocity.linvel.x, velocity.linvel.z);
+        // Water supports the player like the ground does, but slows them down.
+        let supported = grounded.0 || swimming.is_some();
+        let speed = match swimming {
+            Some(_) => player_speed.0 * settings.swim_speed_factor,
+            None => player_speed.0,
+        };
 
-        if direction.length_squared() > 0.0 {
-            player_transform.look_to(direction, Vec3::Y);
+        let horizontal = if direction != Vec3::ZERO {
+            let control = if supported { 1.0 } else { settings.air_control };
+            let target = Vec2::new(direction.x, direction.z) * speed;
+            move_towards(horizontal, target, settings.acceleration * control * delta)
+        } else {
+            let friction = if supported {
+                settings.ground_friction
+            } else {
+                settings.air_friction
+            };
+            move_towards(
+                horizontal,
+                Vec2::ZERO,
+                settings.deceleration * friction * delta,
+            )
+        };
+        velocity.linvel.x = horizontal.x;
+        velocity.linvel.z = horizontal.y;
+
+        if direction != Vec3::ZERO {
+            let target = Transform::IDENTITY.looking_to(direction, Vec3::Y).rotation;
+            let t = 1.0 - (-settings.turn_rate * delta).exp();
+            player_transform.rotation = player_transform.rotation.slerp(target, t);
         }
     }
 }
 
+/// Moves `current` towards `target` by at most `max_delta`.
+fn move_towards(current: Vec2, target: Vec2, max_delta: f32) -> Vec2 {
+    let difference = target - current;
+    let distance = difference.length();
+    if distance <= max_delta || distance == 0.0 {
+        target
+    } else {
+        current + difference / distance * max_delta
+    }
+}
+
+/// Above the spawn point, higher than any terrain, so the chunks around it stream in.
+fn holding_position(settings: &SpawnSettings, config: &NoiseConfig) -> Vec3 {
+    Vec3::new(
+        settings.point.x,
+        config.height_multiplier() + GROUND_PROBE,
+        settings.point.y,
+    )
+}
+
 fn spawn_player(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
+    settings: Res<SpawnSettings>,
+    config: Res<NoiseConfig>,
 ) {
     let player = PbrBundle {
-        mesh: meshes.add(shape::Cube::new(1.0).into()),
+        mesh: meshes.add(shape::Cube::new(PLAYER_HALF_EXTENT * 2.0).into()),
         material: materials.add(Color::GREEN.into()),
-        transform: Transform::from_xyz(0.0, 0.5, 0.0),
+        transform: Transform::from_translation(holding_position(&settings, &config)),
         ..Default::default()
     };
 
     commands
         .spawn((player, Player, MovementSpeed(2.0), ThirdPersonCameraTarget))
+        .insert((MovementSettings::default(), Grounded::default()))
+        .insert((Inventory::new(36), Hotbar::new(9)))
         .insert(RigidBody::Dynamic)
-        .insert(Collider::cuboid(0.5, 0.5, 0.5))
+        .insert((Velocity::default(), GravityScale(1.0), Damping::default()))
+        .insert(LockedAxes::ROTATION_LOCKED)
+        .insert(Collider::cuboid(
+            PLAYER_HALF_EXTENT,
+            PLAYER_HALF_EXTENT,
+            PLAYER_HALF_EXTENT,
+        ))
         .insert(Restitution::coefficient(0.7))
-        .insert(TransformBundle::from(Transform::from_xyz(0.0, 4.0, 0.0)));
+        .insert((
+            Respawning,
+            RigidBodyDisabled,
+            ChunkAnchor(settings.search_radius),
+        ));
+}
+
+fn fall_check(
+    settings: Res<SpawnSettings>,
+    player_query: Query<&Transform, (With<Player>, Without<Respawning>)>,
+    mut died_events: EventWriter<PlayerDied>,
+) {
+    for transform in player_query.iter() {
+        if transform.translation.y < settings.fall_height {
+            died_events.send(PlayerDied);
+        }
+    }
+}
+
+fn respawn_player(
+    mut commands: Commands,
+    mut died_events: EventReader<PlayerDied>,
+    settings: Res<SpawnSettings>,
+    config: Res<NoiseConfig>,
+    mut player_query: Query<(Entity, &mut Transform, &mut Velocity), With<Player>>,
+) {
+    if died_events.read().count() == 0 {
+        return;
+    }
+
+    for (entity, mut transform, mut velocity) in player_query.iter_mut() {
+        transform.translation = holding_position(&settings, &config);
+        *velocity = Velocity::zero();
+        commands.entity(entity).insert((
+            Respawning,
+            RigidBodyDisabled,
+            ChunkAnchor(settings.search_radius),
+        ));
+    }
+}
+
+/// Puts a respawning player on the closest safe ground to the spawn point that nothing
+/// stands on.
+#[allow(clippy::too_many_arguments)]
+fn place_player(
+    mut commands: Commands,
+    settings: Res<SpawnSettings>,
+    config: Res<NoiseConfig>,
+    terrain: Res<Terrain>,
+    rapier_context: Res<RapierContext>,
+    mut loaded_events: EventReader<ChunkLoaded>,
+    mut quiet_frames: Local<u32>,
+    mut player_query: Query<(Entity, &mut Transform, &mut Velocity), With<Respawning>>,
+) {
+    if loaded_events.read().count() > 0 {
+        *quiet_frames = 0;
+        return;
+    }
+    *quiet_frames = quiet_frames.saturating_add(1);
+    if *quiet_frames < SETTLE_FRAMES {
+        return;
+    }
+
+    let body = Collider::cuboid(PLAYER_HALF_EXTENT, PLAYER_HALF_EXTENT, PLAYER_HALF_EXTENT);
+    // Lifted so the bottom of the box clears the ground sloping up under it.
+    let lift = PLAYER_HALF_EXTENT * (1.0 + settings.max_slope) + 0.05;
+
+    for (entity, mut transform, mut velocity) in player_query.iter_mut() {
+        let filter = QueryFilter::default().exclude_rigid_body(entity);
+        let search = terrain.find_spawn(
+            settings.point,
+            settings.search_radius,
+            settings.max_slope,
+            |surface| {
+                // The first thing below has to be the ground, not a tree or a roof.
+                let probe = surface + Vec3::Y * GROUND_PROBE;
+                let (_, distance) = rapier_context.cast_ray(
+                    probe,
+                    Vec3::NEG_Y,
+                    GROUND_PROBE * 2.0,
+                    true,
+                    filter,
+                )?;
+                let ground = probe - Vec3::Y * distance;
+                if (ground.y - surface.y).abs() > GROUND_TOLERANCE {
+                    return None;
+                }
+                let position = ground + Vec3::Y * lift;
+                rapier_context
+                    .intersection_with_shape(position, Quat::IDENTITY, &body, filter)
+                    .is_none()
+                    .then_some(position)
+            },
+        );
+
+        let position = match search {
+            SpawnSearch::Found(position) => position,
+            SpawnSearch::Pending => continue,
+            SpawnSearch::Nothing => {
+                warn!(
+                    "No safe ground within {} cells of the spawn point",
+                    settings.search_radius
+                );
+                let ground = terrain.height_at_world(settings.point).unwrap_or(0.0);
+                let surface = ground.max(config.sea_level().unwrap_or(0.0));
+                Vec3::new(settings.point.x, surface + lift, settings.point.y)
+            }
+        };
+
+        transform.translation = position;
+        *velocity = Velocity::zero();
+        commands
+            .entity(entity)
+            .remove::<(Respawning, RigidBodyDisabled, ChunkAnchor)>();
+    }
 }
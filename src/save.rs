@@ -0,0 +1,313 @@
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    gen::{
+        chunk::{ChunkAnchor, ChunkCoord, ChunkLoaded, ChunkSet},
+        diff::{ChunkDiff, ChunkDiffs},
+        NoiseConfig,
+    },
+    logic::inventory::{Hotbar, Inventory},
+    player::{Player, Respawning},
+    world::{calendar::Calendar, daylight::TimeOfDay},
+};
+
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SaveSettings>()
+            .add_event::<SaveWorld>()
+            .add_event::<LoadWorld>()
+            .add_systems(
+                Update,
+                (save_keys, save_world, load_world).chain().before(ChunkSet),
+            )
+            .add_systems(Update, restore_player.after(ChunkSet));
+    }
+}
+
+/// Version written into new saves. Bump it whenever [`SaveFile`] changes and teach
+/// [`migrate`] to read the previous one.
+pub const SAVE_VERSION: u32 = 1;
+
+#[derive(Resource)]
+pub struct SaveSettings {
+    pub path: PathBuf,
+    pub save_key: KeyCode,
+    pub load_key: KeyCode,
+}
+
+impl Default for SaveSettings {
+    fn default() -> Self {
+        Self {
+            path: PathBuf::from("saves/world.ron"),
+            save_key: KeyCode::F6,
+            load_key: KeyCode::F9,
+        }
+    }
+}
+
+/// Writes the current world to [`SaveSettings::path`].
+#[derive(Event)]
+pub struct SaveWorld;
+
+/// Replaces the current world with the one at [`SaveSettings::path`].
+#[derive(Event)]
+pub struct LoadWorld;
+
+/// Everything needed to bring a world back. The seed is part of `config`, the terrain
+/// itself is regenerated from it with `chunks` applied on top.
+#[derive(Serialize, Deserialize)]
+pub struct SaveFile {
+    pub version: u32,
+    pub config: NoiseConfig,
+    pub player: PlayerSave,
+    pub time: TimeSave,
+    pub chunks: Vec<(IVec2, ChunkDiff)>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PlayerSave {
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub velocity: Vec3,
+    pub inventory: Inventory,
+    pub selected_slot: usize,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct TimeSave {
+    pub hour: f32,
+    pub day: u32,
+    pub month: u32,
+    pub year: u32,
+}
+
+/// Holds a loaded player at their saved position with physics disabled until the chunk
+/// under them has streamed back in, wherever the camera is, then gives them back the saved
+/// velocity.
+#[derive(Component)]
+pub struct Restoring(pub Vec3);
+
+/// Only the part of a save every version shares, read first to pick a migration.
+#[derive(Deserialize)]
+struct SaveHeader {
+    version: u32,
+}
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(io::Error),
+    Serialize(ron::Error),
+    Parse(ron::error::SpannedError),
+    UnsupportedVersion(u32),
+    NoPlayer,
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaveError::Io(err) => write!(f, "could not access save: {err}"),
+            SaveError::Serialize(err) => write!(f, "could not write save: {err}"),
+            SaveError::Parse(err) => write!(f, "could not parse save: {err}"),
+            SaveError::UnsupportedVersion(version) => {
+                write!(f, "save version {version} is not supported")
+            }
+            SaveError::NoPlayer => write!(f, "there is no player to save or load"),
+        }
+    }
+}
+
+impl std::error::Error for SaveError {}
+
+impl SaveFile {
+    pub fn write(&self, path: &Path) -> Result<(), SaveError> {
+        let contents = ron::ser::to_string_pretty(self, PrettyConfig::default())
+            .map_err(SaveError::Serialize)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(SaveError::Io)?;
+        }
+        fs::write(path, contents).map_err(SaveError::Io)
+    }
+
+    pub fn read(path: &Path) -> Result<Self, SaveError> {
+        let contents = fs::read_to_string(path).map_err(SaveError::Io)?;
+        let header: SaveHeader = ron::from_str(&contents).map_err(SaveError::Parse)?;
+        migrate(header.version, &contents)
+    }
+}
+
+/// Reads a save written with `version` into the current [`SaveFile`].
+///
+/// When the format changes, the old layout is kept as its own struct and gets an arm
+/// here that parses it and converts it forward.
+fn migrate(version: u32, contents: &str) -> Result<SaveFile, SaveError> {
+    match version {
+        SAVE_VERSION => ron::from_str(contents).map_err(SaveError::Parse),
+        _ => Err(SaveError::UnsupportedVersion(version)),
+    }
+}
+
+fn save_keys(
+    keys: Res<Input<KeyCode>>,
+    settings: Res<SaveSettings>,
+    mut save_events: EventWriter<SaveWorld>,
+    mut load_events: EventWriter<LoadWorld>,
+) {
+    if keys.just_pressed(settings.save_key) {
+        save_events.send(SaveWorld);
+    }
+    if keys.just_pressed(settings.load_key) {
+        load_events.send(LoadWorld);
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn save_world(
+    mut events: EventReader<SaveWorld>,
+    settings: Res<SaveSettings>,
+    config: Res<NoiseConfig>,
+    diffs: Res<ChunkDiffs>,
+    time_of_day: Res<TimeOfDay>,
+    calendar: Res<Calendar>,
+    player_query: Query<(&Transform, &Velocity, &Inventory, &Hotbar), With<Player>>,
+) {
+    if events.read().count() == 0 {
+        return;
+    }
+
+    let result = player_query
+        .get_single()
+        .map_err(|_| SaveError::NoPlayer)
+        .and_then(|(transform, velocity, inventory, hotbar)| {
+            let mut chunks: Vec<(IVec2, ChunkDiff)> = diffs
+                .0
+                .iter()
+                .map(|(coord, diff)| (coord.0, diff.clone()))
+                .collect();
+            // Keeps the file stable between saves of the same world.
+            chunks.sort_by_key(|(coord, _)| (coord.y, coord.x));
+
+            let save = SaveFile {
+                version: SAVE_VERSION,
+                config: config.clone(),
+                player: PlayerSave {
+                    translation: transform.translation,
+                    rotation: transform.rotation,
+                    velocity: velocity.linvel,
+                    inventory: inventory.clone(),
+                    selected_slot: hotbar.selected,
+                },
+                time: TimeSave {
+                    hour: time_of_day.hour,
+                    day: calendar.day,
+                    month: calendar.month,
+                    year: calendar.year,
+                },
+                chunks,
+            };
+            save.write(&settings.path)
+        });
+
+    match result {
+        Ok(()) => info!("Saved world to {}", settings.path.display()),
+        Err(err) => error!("{err}"),
+    }
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn load_world(
+    mut commands: Commands,
+    mut events: EventReader<LoadWorld>,
+    settings: Res<SaveSettings>,
+    mut config: ResMut<NoiseConfig>,
+    mut diffs: ResMut<ChunkDiffs>,
+    mut time_of_day: ResMut<TimeOfDay>,
+    mut calendar: ResMut<Calendar>,
+    mut player_query: Query<
+        (
+            Entity,
+            &mut Transform,
+            &mut Velocity,
+            &mut Inventory,
+            &mut Hotbar,
+        ),
+        With<Player>,
+    >,
+) {
+    if events.read().count() == 0 {
+        return;
+    }
+
+    let save = match SaveFile::read(&settings.path) {
+        Ok(save) => save,
+        Err(err) => {
+            error!("{err}");
+            return;
+        }
+    };
+    let Ok((entity, mut transform, mut velocity, mut inventory, mut hotbar)) =
+        player_query.get_single_mut()
+    else {
+        error!("{}", SaveError::NoPlayer);
+        return;
+    };
+
+    // Replacing the config makes every chunk regenerate with the restored diffs.
+    *config = save.config;
+    diffs.0 = save
+        .chunks
+        .into_iter()
+        .map(|(coord, diff)| (ChunkCoord(coord), diff))
+        .collect();
+
+    transform.translation = save.player.translation;
+    transform.rotation = save.player.rotation;
+    *velocity = Velocity::zero();
+    // The saved position wins over a spawn point that is still being searched for, but
+    // the ground under it was just dropped along with every other chunk.
+    commands.entity(entity).remove::<Respawning>().insert((
+        Restoring(save.player.velocity),
+        RigidBodyDisabled,
+        ChunkAnchor(0),
+    ));
+    *inventory = save.player.inventory;
+    hotbar.selected = save.player.selected_slot.min(hotbar.size.saturating_sub(1));
+
+    time_of_day.hour = save.time.hour;
+    calendar.day = save.time.day;
+    calendar.month = save.time.month;
+    calendar.year = save.time.year;
+
+    info!("Loaded world from {}", settings.path.display());
+}
+
+fn restore_player(
+    mut commands: Commands,
+    config: Res<NoiseConfig>,
+    mut loaded_events: EventReader<ChunkLoaded>,
+    mut player_query: Query<(Entity, &Transform, &mut Velocity, &Restoring)>,
+) {
+    let loaded: Vec<ChunkCoord> = loaded_events
+        .read()
+        .map(|ChunkLoaded(coord)| *coord)
+        .collect();
+
+    for (entity, transform, mut velocity, restoring) in player_query.iter_mut() {
+        let coord = ChunkCoord::from_world(transform.translation.xz(), config.chunk_size());
+        if loaded.contains(&coord) {
+            velocity.linvel = restoring.0;
+            commands
+                .entity(entity)
+                .remove::<(Restoring, RigidBodyDisabled, ChunkAnchor)>();
+        }
+    }
+}
@@ -0,0 +1,235 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use crate::{gen::NoiseConfig, player::Player};
+
+pub struct WaterPlugin;
+
+impl Plugin for WaterPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<WaterSettings>()
+            .init_resource::<Underwater>()
+            .register_type::<WaterSettings>()
+            .add_systems(Startup, spawn_water)
+            .add_systems(
+                Update,
+                (
+                    (update_water_level, follow_camera),
+                    detect_swimming,
+                    (buoyancy, swim_movement),
+                    detect_underwater,
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// Color of the water surface and of the fog seen from below it.
+const WATER_COLOR: Color = Color::rgba(0.1, 0.3, 0.6, 0.7);
+
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+pub struct WaterSettings {
+    /// Upward push of a fully submerged body, relative to gravity.
+    pub buoyancy: f32,
+    /// Linear damping applied while swimming.
+    pub drag: f32,
+    /// Vertical speed when swimming up or diving.
+    pub swim_vertical_speed: f32,
+}
+
+impl Default for WaterSettings {
+    fn default() -> Self {
+        Self {
+            buoyancy: 1.3,
+            drag: 2.0,
+            swim_vertical_speed: 2.0,
+        }
+    }
+}
+
+/// Set while the active camera is below a water surface.
+#[derive(Resource, Default)]
+pub struct Underwater(pub bool);
+
+/// A box of water whose top is at the entity's `Transform` height.
+#[derive(Component)]
+pub struct WaterVolume {
+    pub half_extents: Vec2,
+    pub depth: f32,
+}
+
+impl WaterVolume {
+    /// Returns how deep `point` is below the surface, if it is inside the volume.
+    pub fn depth_at(&self, transform: &Transform, point: Vec3) -> Option<f32> {
+        let local = point - transform.translation;
+        let inside = local.x.abs() <= self.half_extents.x
+            && local.z.abs() <= self.half_extents.y
+            && local.y <= 0.0
+            && local.y >= -self.depth;
+        inside.then_some(-local.y)
+    }
+}
+
+/// Added to bodies that are at least partly under water.
+#[derive(Component)]
+pub struct Swimming {
+    /// Depth of the body's center below the surface, negative when above it.
+    pub depth: f32,
+}
+
+fn spawn_water(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    config: Res<NoiseConfig>,
+) {
+    let Some(sea_level) = config.sea_level() else {
+        return;
+    };
+    let size = Vec2::splat(config.view_extent());
+
+    commands.spawn((
+        PbrBundle {
+            mesh: meshes.add(shape::Plane::from_size(1.0).into()),
+            material: materials.add(StandardMaterial {
+                base_color: WATER_COLOR,
+                alpha_mode: AlphaMode::Blend,
+                perceptual_roughness: 0.1,
+                ..Default::default()
+            }),
+            transform: Transform::from_xyz(0.0, sea_level, 0.0)
+                .with_scale(Vec3::new(size.x, 1.0, size.y)),
+            ..Default::default()
+        },
+        WaterVolume {
+            half_extents: size / 2.0,
+            depth: sea_level,
+        },
+        Name::new("Water"),
+    ));
+}
+
+fn update_water_level(
+    config: Res<NoiseConfig>,
+    mut water_query: Query<(&mut Transform, &mut WaterVolume)>,
+) {
+    if !config.is_changed() || config.is_added() {
+        return;
+    }
+
+    let sea_level = config.sea_level().unwrap_or(0.0);
+    let size = Vec2::splat(config.view_extent());
+    for (mut transform, mut volume) in water_query.iter_mut() {
+        transform.translation.y = sea_level;
+        transform.scale = Vec3::new(size.x, 1.0, size.y);
+        volume.half_extents = size / 2.0;
+        volume.depth = sea_level;
+    }
+}
+
+/// Keeps the sea centered under the camera, covering the same area as the loaded chunks.
+fn follow_camera(
+    camera_query: Query<&GlobalTransform, With<Camera3d>>,
+    mut water_query: Query<&mut Transform, With<WaterVolume>>,
+) {
+    let Ok(camera) = camera_query.get_single() else {
+        return;
+    };
+    for mut transform in water_query.iter_mut() {
+        transform.translation.x = camera.translation().x;
+        transform.translation.z = camera.translation().z;
+    }
+}
+
+fn detect_swimming(
+    mut commands: Commands,
+    body_query: Query<(Entity, &Transform, Option<&Swimming>), With<Player>>,
+    water_query: Query<(&Transform, &WaterVolume)>,
+) {
+    for (entity, transform, swimming) in body_query.iter() {
+        // Probe half a meter below the center so wading in shallow water counts.
+        let feet = transform.translation - Vec3::Y * 0.5;
+        let depth = water_query
+            .iter()
+            .find_map(|(water_transform, volume)| volume.depth_at(water_transform, feet))
+            .map(|feet_depth| feet_depth - 0.5);
+
+        match (depth, swimming) {
+            (Some(depth), _) => {
+                commands.entity(entity).insert(Swimming { depth });
+            }
+            (None, Some(_)) => {
+                commands.entity(entity).remove::<Swimming>();
+            }
+            (None, None) => {}
+        }
+    }
+}
+
+fn buoyancy(
+    settings: Res<WaterSettings>,
+    mut body_query: Query<(&mut GravityScale, &mut Damping, Option<&Swimming>), With<Player>>,
+) {
+    for (mut gravity, mut damping, swimming) in body_query.iter_mut() {
+        match swimming {
+            Some(swimming) => {
+                let submerged = (swimming.depth + 0.5).clamp(0.0, 1.0);
+                gravity.0 = 1.0 - settings.buoyancy * submerged;
+                damping.linear_damping = settings.drag * submerged;
+            }
+            None => {
+                gravity.0 = 1.0;
+                damping.linear_damping = 0.0;
+            }
+        }
+    }
+}
+
+fn swim_movement(
+    keys: Res<Input<KeyCode>>,
+    time: Res<Time>,
+    settings: Res<WaterSettings>,
+    mut body_query: Query<(&mut Velocity, &Swimming), With<Player>>,
+) {
+    for (mut velocity, swimming) in body_query.iter_mut() {
+        let mut vertical = 0.0;
+        if keys.pressed(KeyCode::Space) && swimming.depth > 0.0 {
+            vertical += settings.swim_vertical_speed;
+        }
+        if keys.pressed(KeyCode::ShiftLeft) {
+            vertical -= settings.swim_vertical_speed;
+        }
+        if vertical != 0.0 {
+            let t = 1.0 - (-5.0 * time.delta_seconds()).exp();
+            velocity.linvel.y += (vertical - velocity.linvel.y) * t;
+        }
+    }
+}
+
+/// Fog seen while the camera is below a water surface.
+pub fn underwater_fog() -> FogSettings {
+    FogSettings {
+        color: WATER_COLOR,
+        falloff: FogFalloff::Exponential { density: 0.15 },
+        ..Default::default()
+    }
+}
+
+/// Keeps [`Underwater`] up to date, the fog itself is applied along with the weather's.
+pub fn detect_underwater(
+    mut underwater: ResMut<Underwater>,
+    camera_query: Query<&GlobalTransform, With<Camera3d>>,
+    water_query: Query<(&Transform, &WaterVolume)>,
+) {
+    for camera_transform in camera_query.iter() {
+        let position = camera_transform.translation();
+        let is_underwater = water_query
+            .iter()
+            .any(|(transform, volume)| volume.depth_at(transform, position).is_some());
+
+        if is_underwater != underwater.0 {
+            underwater.0 = is_underwater;
+        }
+    }
+}
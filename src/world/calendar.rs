@@ -0,0 +1,142 @@
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+
+use crate::gen::TerrainTint;
+
+use super::daylight::{NewDay, TimeOfDay};
+
+pub struct CalendarPlugin;
+
+impl Plugin for CalendarPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Calendar>()
+            .register_type::<Calendar>()
+            .add_event::<SeasonChanged>()
+            .add_systems(Startup, apply_season)
+            .add_systems(Update, (advance_calendar, apply_season).chain());
+    }
+}
+
+pub const MONTHS_PER_YEAR: u32 = 12;
+
+#[derive(Reflect, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Season {
+    Spring,
+    Summer,
+    Autumn,
+    Winter,
+}
+
+impl Season {
+    /// How the season changes terrain colors.
+    pub fn terrain_tint(self) -> TerrainTint {
+        match self {
+            Season::Spring => TerrainTint {
+                snow_line_offset: 0.1,
+                autumn: 0.0,
+            },
+            Season::Summer => TerrainTint::default(),
+            Season::Autumn => TerrainTint {
+                snow_line_offset: 0.05,
+                autumn: 0.6,
+            },
+            Season::Winter => TerrainTint {
+                snow_line_offset: 0.35,
+                autumn: 0.2,
+            },
+        }
+    }
+}
+
+/// In-game date, advanced once per in-game day.
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+pub struct Calendar {
+    /// Day of the month, starting from `0`.
+    pub day: u32,
+    /// Month of the year, starting from `0` at the beginning of spring.
+    pub month: u32,
+    pub year: u32,
+    pub days_per_month: u32,
+}
+
+impl Default for Calendar {
+    fn default() -> Self {
+        Self {
+            day: 0,
+            month: 0,
+            year: 0,
+            days_per_month: 10,
+        }
+    }
+}
+
+impl Calendar {
+    pub fn season(&self) -> Season {
+        match self.month * 4 / MONTHS_PER_YEAR {
+            0 => Season::Spring,
+            1 => Season::Summer,
+            2 => Season::Autumn,
+            _ => Season::Winter,
+        }
+    }
+
+    /// Fraction of the year that has passed, in `0.0..1.0`.
+    pub fn year_progress(&self) -> f32 {
+        let days_per_year = self.days_per_month * MONTHS_PER_YEAR;
+        (self.month * self.days_per_month + self.day) as f32 / days_per_year as f32
+    }
+
+    /// Hours between sunrise and sunset, longest in the middle of summer.
+    pub fn daylight_hours(&self) -> f32 {
+        12.0 + 4.0 * (self.year_progress() * TAU).sin()
+    }
+
+    /// Sea level temperature in degrees, lagging a little behind the daylight curve.
+    pub fn temperature(&self) -> f32 {
+        10.0 + 15.0 * ((self.year_progress() - 0.05) * TAU).sin()
+    }
+
+    pub fn advance_day(&mut self) {
+        self.day += 1;
+        if self.day >= self.days_per_month {
+            self.day = 0;
+            self.month += 1;
+        }
+        if self.month >= MONTHS_PER_YEAR {
+            self.month = 0;
+            self.year += 1;
+        }
+    }
+}
+
+#[derive(Event)]
+pub struct SeasonChanged(pub Season);
+
+fn advance_calendar(
+    mut new_days: EventReader<NewDay>,
+    mut calendar: ResMut<Calendar>,
+    mut season_changed: EventWriter<SeasonChanged>,
+) {
+    for _ in new_days.read() {
+        let season = calendar.season();
+        calendar.advance_day();
+        if calendar.season() != season {
+            season_changed.send(SeasonChanged(calendar.season()));
+        }
+    }
+}
+
+fn apply_season(
+    calendar: Res<Calendar>,
+    mut time_of_day: ResMut<TimeOfDay>,
+    mut tint: ResMut<TerrainTint>,
+) {
+    if !calendar.is_changed() {
+        return;
+    }
+
+    time_of_day.daylight_hours = calendar.daylight_hours();
+    tint.set_if_neq(calendar.season().terrain_tint());
+}
@@ -0,0 +1,212 @@
+use std::f32::consts::PI;
+
+use bevy::prelude::*;
+use bevy_atmosphere::{
+    collection::nishita::Nishita, model::AtmosphereModel, system_param::AtmosphereMut,
+};
+
+pub struct DaylightPlugin;
+
+impl Plugin for DaylightPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(AtmosphereModel::new(Nishita::default()))
+            .init_resource::<TimeOfDay>()
+            .register_type::<TimeOfDay>()
+            .add_event::<NewDay>()
+            .insert_resource(CycleTimer(Timer::new(
+                bevy::utils::Duration::from_millis(100), // Updating the atmosphere every frame is slow
+                TimerMode::Repeating,
+            )))
+            .add_systems(Startup, spawn_celestial_lights)
+            .add_systems(
+                Update,
+                (
+                    time_controls,
+                    advance_time,
+                    daylight_cycle.in_set(DaylightSet),
+                    atmosphere_cycle,
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// Systems that move the sun and moon, anything adjusting lights should run after it.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DaylightSet;
+
+/// Illuminance of the sun at noon, roughly full daylight.
+const SUN_ILLUMINANCE: f32 = 100_000.0;
+const MOON_ILLUMINANCE: f32 = 400.0;
+
+/// Current time of day and how fast it moves.
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+pub struct TimeOfDay {
+    /// Hour of the day in `0.0..24.0`.
+    pub hour: f32,
+    /// Real seconds it takes for a full in-game day to pass.
+    pub day_length: f32,
+    pub paused: bool,
+    /// In-game hours moved per second while scrubbing.
+    pub scrub_speed: f32,
+    /// Hours of daylight, centered on noon.
+    pub daylight_hours: f32,
+}
+
+impl Default for TimeOfDay {
+    fn default() -> Self {
+        Self {
+            hour: 8.0,
+            day_length: 600.0,
+            paused: false,
+            scrub_speed: 4.0,
+            daylight_hours: 12.0,
+        }
+    }
+}
+
+impl TimeOfDay {
+    pub fn sunrise(&self) -> f32 {
+        12.0 - self.daylight_hours / 2.0
+    }
+
+    pub fn sunset(&self) -> f32 {
+        12.0 + self.daylight_hours / 2.0
+    }
+
+    /// Unit vector pointing from the ground towards the sun.
+    ///
+    /// The sun is on the horizon at sunrise and sunset and at its highest at noon,
+    /// daylight hours stretch or squash the arc without moving noon.
+    pub fn sun_direction(&self) -> Vec3 {
+        let (day_start, day_end) = (self.sunrise(), self.sunset());
+        let angle = if (day_start..day_end).contains(&self.hour) {
+            (self.hour - day_start) / self.daylight_hours * PI
+        } else {
+            let night_hours = 24.0 - self.daylight_hours;
+            let since_sunset = (self.hour - day_end).rem_euclid(24.0);
+            PI + since_sunset / night_hours * PI
+        };
+        Vec3::new(-angle.cos(), angle.sin(), 0.3).normalize()
+    }
+
+    /// How high the sun is, `0.0` at the horizon or below and `1.0` straight up.
+    pub fn sun_height(&self) -> f32 {
+        self.sun_direction().y.max(0.0)
+    }
+}
+
+/// Sent when the in-game clock wraps past midnight.
+#[derive(Event)]
+pub struct NewDay;
+
+// Marker for updating the position of the light, not needed unless we have multiple lights
+#[derive(Component)]
+pub struct Sun;
+
+#[derive(Component)]
+pub struct Moon;
+
+// Timer for updating the daylight cycle (updating the atmosphere every frame is slow, so it's better to do incremental changes)
+#[derive(Resource)]
+struct CycleTimer(Timer);
+
+fn spawn_celestial_lights(mut commands: Commands) {
+    commands.spawn((
+        DirectionalLightBundle {
+            directional_light: DirectionalLight {
+                shadows_enabled: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+        Sun, // Marks the light as Sun
+        Name::new("Sun"),
+    ));
+
+    commands.spawn((
+        DirectionalLightBundle {
+            directional_light: DirectionalLight {
+                color: Color::rgb(0.6, 0.7, 1.0),
+                illuminance: 0.0,
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+        Moon,
+        Name::new("Moon"),
+    ));
+}
+
+fn time_controls(keys: Res<Input<KeyCode>>, time: Res<Time>, mut time_of_day: ResMut<TimeOfDay>) {
+    if keys.just_pressed(KeyCode::P) {
+        time_of_day.paused = !time_of_day.paused;
+    }
+
+    let mut scrub = 0.0;
+    if keys.pressed(KeyCode::BracketRight) {
+        scrub += 1.0;
+    }
+    if keys.pressed(KeyCode::BracketLeft) {
+        scrub -= 1.0;
+    }
+    if scrub != 0.0 {
+        let hour = time_of_day.hour + scrub * time_of_day.scrub_speed * time.delta_seconds();
+        time_of_day.hour = hour.rem_euclid(24.0);
+    }
+}
+
+fn advance_time(
+    time: Res<Time>,
+    mut time_of_day: ResMut<TimeOfDay>,
+    mut new_day: EventWriter<NewDay>,
+) {
+    if time_of_day.paused || time_of_day.day_length <= 0.0 {
+        return;
+    }
+
+    let hour = time_of_day.hour + time.delta_seconds() / time_of_day.day_length * 24.0;
+    if hour >= 24.0 {
+        new_day.send(NewDay);
+    }
+    time_of_day.hour = hour.rem_euclid(24.0);
+}
+
+#[allow(clippy::type_complexity)]
+fn daylight_cycle(
+    time_of_day: Res<TimeOfDay>,
+    mut ambient: ResMut<AmbientLight>,
+    mut sun_query: Query<(&mut Transform, &mut DirectionalLight), (With<Sun>, Without<Moon>)>,
+    mut moon_query: Query<(&mut Transform, &mut DirectionalLight), (With<Moon>, Without<Sun>)>,
+) {
+    let sun_direction = time_of_day.sun_direction();
+    let day = time_of_day.sun_height();
+    let night = (-sun_direction.y).max(0.0);
+
+    for (mut transform, mut light) in sun_query.iter_mut() {
+        *transform = Transform::IDENTITY.looking_to(-sun_direction, Vec3::Z);
+        light.illuminance = day.powf(2.0) * SUN_ILLUMINANCE;
+    }
+
+    for (mut transform, mut light) in moon_query.iter_mut() {
+        *transform = Transform::IDENTITY.looking_to(sun_direction, Vec3::Z);
+        light.illuminance = night * MOON_ILLUMINANCE;
+    }
+
+    ambient.color = crate::utils::color_lerp(Color::rgb(0.3, 0.35, 0.6), Color::WHITE, day);
+    ambient.brightness = 0.02 + day * 0.3;
+}
+
+fn atmosphere_cycle(
+    mut atmosphere: AtmosphereMut<Nishita>,
+    mut timer: ResMut<CycleTimer>,
+    time: Res<Time>,
+    time_of_day: Res<TimeOfDay>,
+) {
+    timer.0.tick(time.delta());
+
+    if timer.0.finished() || time_of_day.is_changed() && time_of_day.paused {
+        atmosphere.sun_position = time_of_day.sun_direction();
+    }
+}
@@ -0,0 +1,19 @@
+use bevy::prelude::*;
+
+pub mod calendar;
+pub mod daylight;
+pub mod weather;
+
+use calendar::CalendarPlugin;
+use daylight::DaylightPlugin;
+use weather::WeatherPlugin;
+
+/// Lighting and environment. The ground itself is streamed in by
+/// [`MapPlugin`](crate::gen::MapPlugin) from the shared [`NoiseConfig`](crate::gen::NoiseConfig).
+pub struct WorldPlugin;
+
+impl Plugin for WorldPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((DaylightPlugin, CalendarPlugin, WeatherPlugin));
+    }
+}
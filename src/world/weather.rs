@@ -0,0 +1,439 @@
+use bevy::prelude::*;
+use bevy_atmosphere::{collection::nishita::Nishita, system_param::AtmosphereMut};
+use rand::{seq::SliceRandom, Rng};
+
+use crate::{
+    gen::{biome::Biome, chunk::Terrain},
+    player::Player,
+    water::{detect_underwater, underwater_fog, Underwater},
+};
+
+use super::{
+    calendar::{Calendar, Season},
+    daylight::{DaylightSet, Sun},
+};
+
+pub struct WeatherPlugin;
+
+impl Plugin for WeatherPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<WeatherState>()
+            .init_resource::<Wind>()
+            .register_type::<WeatherState>()
+            .register_type::<Wind>()
+            .add_event::<WeatherEvent>()
+            .add_systems(Startup, spawn_precipitation)
+            .add_systems(
+                Update,
+                (
+                    change_weather,
+                    blend_weather,
+                    update_wind,
+                    send_weather_events,
+                    (
+                        dim_sun.after(DaylightSet),
+                        weather_fog.after(detect_underwater),
+                        weather_atmosphere,
+                    ),
+                    animate_precipitation,
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// Number of rain drops or snow flakes kept around the camera.
+const PRECIPITATION_PARTICLES: usize = 400;
+/// Radius around the camera in which precipitation is drawn.
+const PRECIPITATION_RADIUS: f32 = 12.0;
+/// Mie scattering coefficient of a clear `Nishita` sky.
+const CLEAR_MIE_COEFFICIENT: f32 = 21e-6;
+
+#[derive(Reflect, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Weather {
+    #[default]
+    Clear,
+    Overcast,
+    Rain,
+    Storm,
+    Snow,
+    Fog,
+}
+
+/// How a weather looks and feels, blended while the weather changes.
+#[derive(Reflect, Clone, Copy, Debug, Default, PartialEq)]
+pub struct WeatherProfile {
+    /// Fraction of sunlight blocked by clouds.
+    pub cloud_cover: f32,
+    /// Distance in meters at which objects fade into the fog.
+    pub visibility: f32,
+    /// Amount of rain or snow in `0.0..=1.0`.
+    pub precipitation: f32,
+    pub wind_strength: f32,
+}
+
+impl WeatherProfile {
+    fn lerp(self, other: Self, t: f32) -> Self {
+        let lerp = |a: f32, b: f32| a + (b - a) * t;
+        Self {
+            cloud_cover: lerp(self.cloud_cover, other.cloud_cover),
+            visibility: lerp(self.visibility, other.visibility),
+            precipitation: lerp(self.precipitation, other.precipitation),
+            wind_strength: lerp(self.wind_strength, other.wind_strength),
+        }
+    }
+}
+
+impl Weather {
+    pub fn profile(self) -> WeatherProfile {
+        let (cloud_cover, visibility, precipitation, wind_strength) = match self {
+            Weather::Clear => (0.0, 1000.0, 0.0, 2.0),
+            Weather::Overcast => (0.6, 400.0, 0.0, 4.0),
+            Weather::Rain => (0.75, 150.0, 0.6, 6.0),
+            Weather::Storm => (0.9, 80.0, 1.0, 14.0),
+            Weather::Snow => (0.7, 120.0, 0.7, 3.0),
+            Weather::Fog => (0.4, 30.0, 0.0, 0.5),
+        };
+        WeatherProfile {
+            cloud_cover,
+            visibility,
+            precipitation,
+            wind_strength,
+        }
+    }
+
+    /// Whether surfaces get wet while this weather lasts.
+    pub fn is_wet(self) -> bool {
+        matches!(self, Weather::Rain | Weather::Storm)
+    }
+
+    /// Relative chance of each weather following in a biome and season.
+    ///
+    /// Rain turns into snow below freezing and the other way round.
+    pub fn weights(biome: Biome, season: Season, temperature: f32) -> Vec<(Weather, f32)> {
+        let (clear, overcast, wet, storm, fog) = match biome {
+            Biome::Desert => (8.0, 1.0, 0.2, 0.2, 0.1),
+            Biome::Ocean | Biome::Beach => (3.0, 2.0, 2.0, 1.0, 1.0),
+            Biome::Forest | Biome::Taiga => (3.0, 2.0, 2.5, 0.5, 1.5),
+            Biome::Tundra | Biome::Snow => (3.0, 2.0, 2.0, 0.5, 0.5),
+            Biome::Grassland => (5.0, 2.0, 1.5, 0.5, 0.5),
+        };
+        let (wet, storm, fog) = match season {
+            Season::Spring => (wet * 1.5, storm, fog),
+            Season::Summer => (wet * 0.7, storm * 2.0, fog * 0.5),
+            Season::Autumn => (wet * 1.5, storm, fog * 2.0),
+            Season::Winter => (wet, storm * 0.5, fog * 1.5),
+        };
+
+        let (precipitation, storm) = if temperature < 1.0 {
+            (Weather::Snow, 0.0)
+        } else {
+            (Weather::Rain, storm)
+        };
+
+        vec![
+            (Weather::Clear, clear),
+            (Weather::Overcast, overcast),
+            (precipitation, wet),
+            (Weather::Storm, storm),
+            (Weather::Fog, fog),
+        ]
+    }
+}
+
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+pub struct WeatherState {
+    pub current: Weather,
+    pub previous: Weather,
+    /// Progress of the change from `previous` to `current`, in `0.0..=1.0`.
+    pub blend: f32,
+    /// Seconds a change of weather takes.
+    pub transition_time: f32,
+    /// Real seconds a weather lasts, picked at random from this range.
+    pub duration: Vec2,
+    /// Time left until the next change of weather.
+    pub remaining: f32,
+    /// Current mix of `previous` and `current`.
+    pub profile: WeatherProfile,
+    wet: bool,
+    low_visibility: bool,
+}
+
+impl Default for WeatherState {
+    fn default() -> Self {
+        Self {
+            current: Weather::Clear,
+            previous: Weather::Clear,
+            blend: 1.0,
+            transition_time: 20.0,
+            duration: Vec2::new(60.0, 240.0),
+            remaining: 120.0,
+            profile: Weather::Clear.profile(),
+            wet: false,
+            low_visibility: false,
+        }
+    }
+}
+
+impl WeatherState {
+    /// Starts changing to `weather`, skipping the transition if `instant` is set.
+    pub fn set(&mut self, weather: Weather, instant: bool) {
+        self.previous = self.current;
+        self.current = weather;
+        self.blend = if instant { 1.0 } else { 0.0 };
+    }
+}
+
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+pub struct Wind {
+    /// Normalized direction on the XZ plane the wind blows towards.
+    pub direction: Vec2,
+    /// Wind speed in meters per second.
+    pub strength: f32,
+}
+
+impl Default for Wind {
+    fn default() -> Self {
+        Self {
+            direction: Vec2::X,
+            strength: 0.0,
+        }
+    }
+}
+
+impl Wind {
+    pub fn velocity(&self) -> Vec3 {
+        Vec3::new(self.direction.x, 0.0, self.direction.y) * self.strength
+    }
+}
+
+/// Things gameplay can react to when the weather changes.
+#[derive(Event, Clone, Copy, Debug, PartialEq)]
+pub enum WeatherEvent {
+    Changed {
+        previous: Weather,
+        current: Weather,
+    },
+    /// Surfaces became wet or dried up.
+    SurfacesWet(bool),
+    /// Visibility dropped below or went back above [`LOW_VISIBILITY`].
+    VisibilityReduced(bool),
+}
+
+/// Visibility in meters below which it counts as reduced.
+pub const LOW_VISIBILITY: f32 = 100.0;
+
+#[derive(Component)]
+struct Precipitation;
+
+fn change_weather(
+    time: Res<Time>,
+    calendar: Res<Calendar>,
+    terrain: Res<Terrain>,
+    player_query: Query<&Transform, With<Player>>,
+    mut state: ResMut<WeatherState>,
+    mut events: EventWriter<WeatherEvent>,
+) {
+    state.remaining -= time.delta_seconds();
+    if state.remaining > 0.0 {
+        return;
+    }
+
+    let mut rng = rand::thread_rng();
+    state.remaining = rng.gen_range(state.duration.x..=state.duration.y.max(state.duration.x));
+
+    let temperature = calendar.temperature();
+    let biome = match player_query.get_single() {
+        Ok(player) => terrain
+            .biome_at_world(player.translation.xz(), temperature)
+            .unwrap_or_default(),
+        Err(_) => Biome::default(),
+    };
+
+    let weights = Weather::weights(biome, calendar.season(), temperature);
+    let Ok((next, _)) = weights.choose_weighted(&mut rng, |(_, weight)| *weight) else {
+        return;
+    };
+
+    if *next != state.current {
+        let previous = state.current;
+        state.set(*next, false);
+        events.send(WeatherEvent::Changed {
+            previous,
+            current: *next,
+        });
+    }
+}
+
+fn blend_weather(time: Res<Time>, mut state: ResMut<WeatherState>) {
+    if state.blend < 1.0 {
+        let step = time.delta_seconds() / state.transition_time.max(f32::EPSILON);
+        state.blend = (state.blend + step).min(1.0);
+    }
+
+    let profile = state
+        .previous
+        .profile()
+        .lerp(state.current.profile(), state.blend);
+    if state.profile != profile {
+        state.profile = profile;
+    }
+}
+
+fn update_wind(time: Res<Time>, state: Res<WeatherState>, mut wind: ResMut<Wind>) {
+    let t = 1.0 - (-0.5 * time.delta_seconds()).exp();
+    wind.strength += (state.profile.wind_strength - wind.strength) * t;
+
+    // Slowly veer the wind back and forth, gusty weather turns it faster.
+    let turn_rate = (time.elapsed_seconds() * 0.05).sin() * 0.05 * (1.0 + wind.strength * 0.1);
+    let rotation = Vec2::from_angle(turn_rate * time.delta_seconds());
+    wind.direction = rotation.rotate(wind.direction).normalize_or_zero();
+}
+
+fn send_weather_events(mut state: ResMut<WeatherState>, mut events: EventWriter<WeatherEvent>) {
+    let wet = state.current.is_wet() && state.blend > 0.5;
+    if wet != state.wet {
+        state.wet = wet;
+        events.send(WeatherEvent::SurfacesWet(wet));
+    }
+
+    let low_visibility = state.profile.visibility < LOW_VISIBILITY;
+    if low_visibility != state.low_visibility {
+        state.low_visibility = low_visibility;
+        events.send(WeatherEvent::VisibilityReduced(low_visibility));
+    }
+}
+
+fn dim_sun(state: Res<WeatherState>, mut sun_query: Query<&mut DirectionalLight, With<Sun>>) {
+    for mut light in sun_query.iter_mut() {
+        light.illuminance *= 1.0 - state.profile.cloud_cover * 0.9;
+    }
+}
+
+/// Sets the camera fog, the water's while the camera is submerged and the weather's
+/// otherwise.
+fn weather_fog(
+    mut commands: Commands,
+    state: Res<WeatherState>,
+    underwater: Res<Underwater>,
+    mut applied: Local<Option<(WeatherProfile, bool)>>,
+    mut camera_query: Query<(Entity, Option<&mut FogSettings>), With<Camera3d>>,
+) {
+    let wanted = Some((state.profile, underwater.0));
+    let unfogged = camera_query.iter().any(|(_, fog)| fog.is_none());
+    if *applied == wanted && !unfogged {
+        return;
+    }
+    *applied = wanted;
+
+    let fog = if underwater.0 {
+        underwater_fog()
+    } else {
+        let gray = 0.8 - state.profile.cloud_cover * 0.4;
+        FogSettings {
+            color: Color::rgba(gray, gray, gray + 0.05, 1.0),
+            falloff: FogFalloff::from_visibility(state.profile.visibility),
+            ..Default::default()
+        }
+    };
+    for (entity, current) in camera_query.iter_mut() {
+        match current {
+            Some(mut current) => *current = fog.clone(),
+            None => {
+                commands.entity(entity).insert(fog.clone());
+            }
+        }
+    }
+}
+
+fn weather_atmosphere(
+    state: Res<WeatherState>,
+    mut applied: Local<Option<WeatherProfile>>,
+    mut atmosphere: AtmosphereMut<Nishita>,
+) {
+    // The state changes every frame as the weather counts down, only the profile matters.
+    if *applied == Some(state.profile) {
+        return;
+    }
+    *applied = Some(state.profile);
+
+    let haze = state.profile.cloud_cover + (1000.0 - state.profile.visibility) / 1000.0;
+    atmosphere.mie_coefficient = CLEAR_MIE_COEFFICIENT * (1.0 + haze * 4.0);
+}
+
+fn spawn_precipitation(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let mesh = meshes.add(shape::Box::new(0.02, 0.3, 0.02).into());
+    let material = materials.add(StandardMaterial {
+        base_color: Color::rgba(0.8, 0.85, 1.0, 0.6),
+        alpha_mode: AlphaMode::Blend,
+        unlit: true,
+        ..Default::default()
+    });
+
+    let mut rng = rand::thread_rng();
+    for _ in 0..PRECIPITATION_PARTICLES {
+        let offset = Vec3::new(
+            rng.gen_range(-PRECIPITATION_RADIUS..PRECIPITATION_RADIUS),
+            rng.gen_range(-PRECIPITATION_RADIUS..PRECIPITATION_RADIUS),
+            rng.gen_range(-PRECIPITATION_RADIUS..PRECIPITATION_RADIUS),
+        );
+        commands.spawn((
+            PbrBundle {
+                mesh: mesh.clone(),
+                material: material.clone(),
+                transform: Transform::from_translation(offset),
+                visibility: Visibility::Hidden,
+                ..Default::default()
+            },
+            Precipitation,
+        ));
+    }
+}
+
+/// Drops particles around the camera, wrapping them inside a box so they never run out.
+fn animate_precipitation(
+    time: Res<Time>,
+    state: Res<WeatherState>,
+    wind: Res<Wind>,
+    camera_query: Query<&GlobalTransform, With<Camera3d>>,
+    mut particle_query: Query<(&mut Transform, &mut Visibility), With<Precipitation>>,
+) {
+    let Ok(camera) = camera_query.get_single() else {
+        return;
+    };
+    let center = camera.translation();
+    let snowing = state.current == Weather::Snow;
+    let fall_speed = if snowing { 1.5 } else { 12.0 };
+    let velocity = Vec3::NEG_Y * fall_speed + wind.velocity() * if snowing { 0.5 } else { 0.2 };
+    let visible = (state.profile.precipitation * PRECIPITATION_PARTICLES as f32) as usize;
+    let size = PRECIPITATION_RADIUS * 2.0;
+
+    for (index, (mut transform, mut visibility)) in particle_query.iter_mut().enumerate() {
+        let shown = index < visible;
+        let new_visibility = if shown {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+        visibility.set_if_neq(new_visibility);
+        if !shown {
+            continue;
+        }
+
+        let local = transform.translation - center + velocity * time.delta_seconds();
+        let wrapped =
+            (local + PRECIPITATION_RADIUS).rem_euclid(Vec3::splat(size)) - PRECIPITATION_RADIUS;
+        transform.translation = center + wrapped;
+        transform.scale = if snowing {
+            Vec3::new(3.0, 0.2, 3.0)
+        } else {
+            Vec3::ONE
+        };
+    }
+}